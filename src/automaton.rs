@@ -1,5 +1,29 @@
 mod dfa;
+mod lazy_dfa;
 mod nfa;
 
 pub use crate::automaton::dfa::*;
+pub use crate::automaton::lazy_dfa::*;
 pub use crate::automaton::nfa::*;
+
+/// Returns the id of the `index`-th state. Panics instead of wrapping around once the ids run out,
+/// keeping `u32::MAX` free so that the number of states fits in a `u32`, too.
+fn state_id(index: usize) -> u32 {
+    match u32::try_from(index) {
+        Ok(id) if id < u32::MAX => id,
+        _ => panic!("too many states, the ids of the states are u32"),
+    }
+}
+
+/// An automaton that can be simulated character by character.
+pub trait Automaton {
+    type State;
+
+    fn start_state(&self) -> Self::State;
+
+    /// Returns `None` if no state can be transitioned by `char`, or if no accept state can be
+    /// reached from the state transitioned to, so that a simulation can stop early.
+    fn step(&self, state: &Self::State, char: char) -> Option<Self::State>;
+
+    fn is_accept(&self, state: &Self::State) -> bool;
+}
@@ -1,8 +1,12 @@
 use crate::automaton::nfa::{NFAState, NondeterministicFiniteAutomaton};
-use std::collections::{HashMap, HashSet};
+use crate::automaton::{state_id, Automaton};
+use crate::error::{DeserializeError, TableError};
+use crate::prelude::hash_map::Entry;
+use crate::prelude::*;
+use alloc::collections::{BTreeMap, BTreeSet, VecDeque};
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
-pub struct DFAState(u32);
+pub struct DFAState(pub u32);
 
 struct Context {
     state_count: u32,
@@ -23,8 +27,8 @@ impl Context {
         match self.state_map.get(&sorted_states) {
             Some(state) => *state,
             None => {
-                let id = self.state_count;
-                self.state_count += 1;
+                let id = state_id(self.state_count as usize);
+                self.state_count = id + 1;
                 self.state_map.insert(sorted_states, DFAState(id));
                 DFAState(id)
             }
@@ -32,107 +36,1129 @@ impl Context {
     }
 }
 
+#[derive(Clone)]
 pub struct DeterministicFiniteAutomaton {
-    pub start: DFAState,
-    pub accepts: HashSet<DFAState>,
+    start: DFAState,
+    state_count: u32,
+    accepts: HashSet<DFAState>,
     transition: HashMap<(DFAState, char), DFAState>,
+    default_transition: HashMap<DFAState, DFAState>,
+    /// alphabet[char] = The symbol of a character that has its own transition from some state.
+    /// The symbol 0 stands for every other character.
+    alphabet: HashMap<char, usize>,
+    /// ascii_alphabet[char] = `alphabet[char]` for an ASCII character, to skip the hashing.
+    ascii_alphabet: Box<[usize; 128]>,
+    /// table[state * stride + symbol] = The dense form of `transition` and `default_transition`,
+    /// used for matching. `stride` is the number of symbols.
+    table: Vec<Option<DFAState>>,
+    stride: usize,
+    /// is_accept[state] = The dense form of `accepts`.
+    is_accept: Vec<bool>,
+    /// is_dead[state] = True if no accept state is reachable from `state`.
+    is_dead: Vec<bool>,
 }
 
 impl DeterministicFiniteAutomaton {
+    /// Builds the automaton from the sparse transitions, also computing their dense form.
+    fn new(
+        start: DFAState,
+        state_count: u32,
+        accepts: HashSet<DFAState>,
+        transition: HashMap<(DFAState, char), DFAState>,
+        default_transition: HashMap<DFAState, DFAState>,
+    ) -> Self {
+        let mut alphabet = HashMap::<char, usize>::new();
+        for char in transition
+            .keys()
+            .map(|(_, char)| *char)
+            .collect::<BTreeSet<_>>()
+        {
+            alphabet.insert(char, alphabet.len() + 1);
+        }
+        let mut ascii_alphabet = Box::new([0; 128]);
+        for (char, symbol) in &alphabet {
+            if char.is_ascii() {
+                ascii_alphabet[*char as usize] = *symbol;
+            }
+        }
+        let stride = alphabet.len() + 1;
+        let mut table = (0..state_count)
+            .flat_map(|state| {
+                let default = default_transition.get(&DFAState(state)).cloned();
+                core::iter::repeat_n(default, stride)
+            })
+            .collect::<Vec<_>>();
+        for ((from, char), to) in &transition {
+            table[from.0 as usize * stride + alphabet[char]] = Some(*to);
+        }
+        let mut is_accept = vec![false; state_count as usize];
+        for state in &accepts {
+            is_accept[state.0 as usize] = true;
+        }
+        let mut dfa = DeterministicFiniteAutomaton {
+            start,
+            state_count,
+            accepts,
+            transition,
+            default_transition,
+            alphabet,
+            ascii_alphabet,
+            table,
+            stride,
+            is_accept,
+            is_dead: vec![],
+        };
+        dfa.is_dead = dfa.dead_states();
+        dfa
+    }
+
     pub fn from_nfa(nfa: NondeterministicFiniteAutomaton) -> Self {
-        let mut context = Context::new();
+        Self::from_nfa_with_tags(nfa, &HashMap::new()).0
+    }
 
-        let (start, start_states) = {
-            let mut ret = vec![nfa.start];
-            let mut stack = nfa
-                .next_states(nfa.start, None)
-                .iter()
-                .cloned()
-                .collect::<Vec<_>>();
-            while let Some(state) = stack.pop() {
-                ret.push(state);
-                let next = nfa.next_states(state, None);
-                stack.extend(next.iter().filter(|s| !ret.contains(s)).cloned());
+    /// Like `from_nfa`, but also returns the tags each state carries, which are the tags of the
+    /// NFA states it stands for. Tagging the accept states tells which of them a match ends in.
+    pub fn from_nfa_with_tags(
+        nfa: NondeterministicFiniteAutomaton,
+        tags: &HashMap<NFAState, usize>,
+    ) -> (Self, HashMap<DFAState, BTreeSet<usize>>) {
+        Self::determinize(nfa, tags, usize::MAX).unwrap()
+    }
+
+    /// Like `from_nfa`, but returns `None` as soon as the subset construction finds more than
+    /// `max_states` states, counting the dead state that is pruned in the end.
+    pub(crate) fn from_nfa_with_limit(
+        nfa: NondeterministicFiniteAutomaton,
+        max_states: usize,
+    ) -> Option<Self> {
+        Some(Self::determinize(nfa, &HashMap::new(), max_states)?.0)
+    }
+
+    fn determinize(
+        nfa: NondeterministicFiniteAutomaton,
+        tags: &HashMap<NFAState, usize>,
+        max_states: usize,
+    ) -> Option<(Self, HashMap<DFAState, BTreeSet<usize>>)> {
+        let mut context = Context::new();
+        // closures[state] = The epsilon closure of `state`, computed once when first needed.
+        let mut closures = HashMap::<NFAState, HashSet<NFAState>>::new();
+        let mut epsilon_closure = |states: HashSet<NFAState>| {
+            let mut ret = HashSet::<NFAState>::new();
+            // The closure of a state already in `ret` is in it, too.
+            for state in states {
+                if ret.contains(&state) {
+                    continue;
+                }
+                let closure = closures
+                    .entry(state)
+                    .or_insert_with(|| nfa.epsilon_closure(state));
+                ret.extend(closure.iter().cloned());
             }
-            (context.get_state(&ret), ret)
+            ret.into_iter().collect::<Vec<_>>()
         };
 
-        let transition = {
+        let start_states = epsilon_closure([nfa.start()].into());
+        let start = context.get_state(&start_states);
+
+        let (transition, default_transition) = {
             let mut ret = HashMap::<(DFAState, char), DFAState>::new();
+            let mut default_ret = HashMap::<DFAState, DFAState>::new();
             let mut waiting = vec![start_states];
             let mut visited = HashSet::<DFAState>::new();
             while let Some(look_states) = waiting.pop() {
-                visited.insert(context.get_state(&look_states));
+                if context.state_count as usize > max_states {
+                    return None;
+                }
+                let form_state = context.get_state(&look_states);
+                if !visited.insert(form_state) {
+                    continue;
+                }
 
                 // Collect states that can be transitioned from the current state (look_states).
                 // transition_map[char] = The set of states that can be transitioned by `char`.
-                let mut transition_map = HashMap::<char, HashSet<NFAState>>::new();
+                // any_states = The set of states that can be transitioned by any other character.
+                // The characters are visited in order, so that the states are numbered the same
+                // way on every run.
+                let mut transition_map = BTreeMap::<char, HashSet<NFAState>>::new();
+                let mut any_states = HashSet::<NFAState>::new();
                 for look_state in &look_states {
-                    for char in nfa
-                        .next_chars(*look_state)
-                        .iter()
-                        .filter_map(|c| c.is_some().then(|| c.unwrap()))
-                    {
-                        let mut next_states = nfa
-                            .next_states(*look_state, Some(char))
-                            .into_iter()
-                            .chain(nfa.next_states(*look_state, None))
-                            .collect::<Vec<_>>();
-                        let mut stack = next_states
-                            .iter()
-                            .filter(|s| !nfa.next_states(**s, None).is_empty())
-                            .cloned()
-                            .collect::<Vec<_>>();
-                        while let Some(state) = stack.pop() {
-                            let next = nfa.next_states(state, None);
-                            stack.extend(next.iter().filter(|s| !next_states.contains(s)).cloned());
-                            next_states.extend(next);
-                        }
-                        transition_map
-                            .entry(char)
-                            .or_insert(HashSet::new())
-                            .extend(next_states);
+                    let chars = nfa.next_chars(*look_state).into_iter().flatten();
+                    for char in chars.chain(nfa.excluded_chars(*look_state)) {
+                        transition_map.entry(char).or_default();
+                    }
+                    any_states.extend(nfa.next_any_states(*look_state, None));
+                }
+                for (char, next_states) in transition_map.iter_mut() {
+                    for look_state in &look_states {
+                        next_states.extend(nfa.next_states(*look_state, Some(*char)));
+                        next_states.extend(nfa.next_any_states(*look_state, Some(*char)));
                     }
                 }
 
-                let form_state = context.get_state(&look_states);
+                // A character without its own entry falls back to the default transition,
+                // so an entry is kept even if it leads to the empty (dead) set of states.
                 for (char, next_states) in transition_map {
-                    let next_states_vec: Vec<_> = next_states.iter().cloned().collect();
-                    let to_state = context.get_state(&next_states_vec);
+                    let next_states = epsilon_closure(next_states);
+                    let to_state = context.get_state(&next_states);
                     if !visited.contains(&to_state) {
-                        waiting.push(next_states.into_iter().collect());
+                        waiting.push(next_states);
                     }
                     ret.insert((form_state, char), to_state);
                 }
+                if !any_states.is_empty() {
+                    let next_states = epsilon_closure(any_states);
+                    let to_state = context.get_state(&next_states);
+                    if !visited.contains(&to_state) {
+                        waiting.push(next_states);
+                    }
+                    default_ret.insert(form_state, to_state);
+                }
             }
-            ret
+            if context.state_count as usize > max_states {
+                return None;
+            }
+            (ret, default_ret)
         };
 
         let accepts = {
             let mut ret = HashSet::<DFAState>::new();
-            for (nfa_states, dfa_state) in context.state_map {
-                if nfa_states.iter().any(|s| nfa.accepts.contains(s)) {
-                    ret.insert(dfa_state);
+            for (nfa_states, dfa_state) in &context.state_map {
+                if nfa_states.iter().any(|s| nfa.accepts().contains(s)) {
+                    ret.insert(*dfa_state);
                 }
             }
             ret
         };
 
-        DeterministicFiniteAutomaton {
+        let (dfa, state_map) = DeterministicFiniteAutomaton::new(
             start,
+            context.state_count,
             accepts,
             transition,
+            default_transition,
+        )
+        .prune_with_state_map();
+
+        let mut dfa_tags = HashMap::<DFAState, BTreeSet<usize>>::new();
+        for (nfa_states, dfa_state) in &context.state_map {
+            let Some(dfa_state) = state_map.get(dfa_state) else {
+                continue;
+            };
+            for tag in nfa_states.iter().filter_map(|state| tags.get(state)) {
+                dfa_tags.entry(*dfa_state).or_default().insert(*tag);
+            }
         }
+        Some((dfa, dfa_tags))
+    }
+
+    pub fn start(&self) -> DFAState {
+        self.start
+    }
+
+    /// Returns the number of states, whose ids are `0..state_count`.
+    pub fn state_count(&self) -> u32 {
+        self.state_count
+    }
+
+    pub fn accepts(&self) -> &HashSet<DFAState> {
+        &self.accepts
+    }
+
+    /// Returns the characters that have a transition of their own from some state.
+    pub fn alphabet(&self) -> BTreeSet<char> {
+        self.alphabet.keys().cloned().collect()
+    }
+
+    /// Returns the transitions by a specific character, in arbitrary order.
+    pub fn transitions(&self) -> impl Iterator<Item = (DFAState, char, DFAState)> + '_ {
+        self.transition
+            .iter()
+            .map(|((from, char), to)| (*from, *char, *to))
+    }
+
+    /// Returns the transitions by any character that has no transition of its own from the state,
+    /// in arbitrary order.
+    pub fn default_transitions(&self) -> impl Iterator<Item = (DFAState, DFAState)> + '_ {
+        self.default_transition
+            .iter()
+            .map(|(from, to)| (*from, *to))
     }
 
     pub fn next_state(&self, state: DFAState, char: char) -> Option<DFAState> {
-        self.transition.get(&(state, char)).cloned()
+        let symbol = if char.is_ascii() {
+            self.ascii_alphabet[char as usize]
+        } else {
+            self.alphabet.get(&char).cloned().unwrap_or(0)
+        };
+        self.table[state.0 as usize * self.stride + symbol]
+    }
+
+    /// Returns the transitions from `state` by every symbol, see `alphabet`.
+    fn row(&self, state: usize) -> &[Option<DFAState>] {
+        &self.table[state * self.stride..(state + 1) * self.stride]
+    }
+
+    /// Returns the same automaton with a transition from every state by every character of
+    /// `alphabet`. The missing ones are routed to a new non-accepting sink state, which loops on
+    /// every character of `alphabet`.
+    pub fn complete(self, alphabet: &BTreeSet<char>) -> Self {
+        let sink = DFAState(state_id(self.state_count as usize));
+        let missing = (0..self.state_count)
+            .map(DFAState)
+            .flat_map(|state| alphabet.iter().map(move |char| (state, *char)))
+            .filter(|(state, char)| self.next_state(*state, *char).is_none())
+            .collect::<Vec<_>>();
+        if missing.is_empty() {
+            return self;
+        }
+        let mut transition = self.transition;
+        for (state, char) in missing {
+            transition.insert((state, char), sink);
+        }
+        for char in alphabet {
+            transition.insert((sink, *char), sink);
+        }
+        DeterministicFiniteAutomaton::new(
+            self.start,
+            self.state_count + 1,
+            self.accepts,
+            transition,
+            self.default_transition,
+        )
+    }
+
+    /// Returns the automaton accepting exactly the strings this automaton rejects.
+    /// The automaton is first completed with a sink state, which every missing transition leads to.
+    pub fn complement(&self) -> Self {
+        let mut state_count = self.state_count;
+        let mut default_transition = self.default_transition.clone();
+        let states = (0..self.state_count).map(DFAState);
+        if states.clone().any(|s| !default_transition.contains_key(&s)) {
+            let sink = DFAState(state_id(state_count as usize));
+            state_count += 1;
+            for state in (0..state_count).map(DFAState) {
+                default_transition.entry(state).or_insert(sink);
+            }
+        }
+        let accepts = (0..state_count)
+            .map(DFAState)
+            .filter(|s| !self.accepts.contains(s))
+            .collect();
+        DeterministicFiniteAutomaton::new(
+            self.start,
+            state_count,
+            accepts,
+            self.transition.clone(),
+            default_transition,
+        )
+    }
+
+    /// Returns the automaton accepting the strings both automata accept.
+    pub fn intersect(&self, other: &Self) -> Self {
+        self.product(other, |accept1, accept2| accept1 && accept2)
+    }
+
+    /// Returns the automaton accepting the strings exactly one of the automata accepts.
+    pub fn symmetric_difference(&self, other: &Self) -> Self {
+        // The complements are complete, so their product follows every string on both sides even
+        // if the alphabets differ. A string is accepted by exactly one of the complements iff it
+        // is accepted by exactly one of the automata, i.e. iff it is in (A ∩ ¬B) ∪ (¬A ∩ B).
+        self.complement()
+            .product(&other.complement(), |accept1, accept2| accept1 != accept2)
+    }
+
+    /// Returns true if both automata accept exactly the same strings.
+    pub fn equivalent(&self, other: &Self) -> bool {
+        self.symmetric_difference(other).is_empty()
+    }
+
+    /// Returns true if every string this automaton accepts is accepted by `other`, too.
+    pub fn is_subset_of(&self, other: &Self) -> bool {
+        self.intersect(&other.complement()).is_empty()
+    }
+
+    /// Runs both automata in parallel. A pair of states exists only while both automata can
+    /// transition, and it is accepting iff `is_accept` holds for the acceptance of both states.
+    fn product(&self, other: &Self, is_accept: impl Fn(bool, bool) -> bool) -> Self {
+        let chars1 = self.transition_chars();
+        let chars2 = other.transition_chars();
+
+        let mut state_map = HashMap::<(DFAState, DFAState), DFAState>::new();
+        let mut get_state = |pair: (DFAState, DFAState), waiting: &mut Vec<_>| {
+            let len = state_map.len();
+            *state_map.entry(pair).or_insert_with(|| {
+                waiting.push(pair);
+                DFAState(state_id(len))
+            })
+        };
+
+        let mut waiting = vec![];
+        let start = get_state((self.start, other.start), &mut waiting);
+        let mut accepts = HashSet::<DFAState>::new();
+        let mut transition = HashMap::<(DFAState, char), DFAState>::new();
+        let mut default_transition = HashMap::<DFAState, DFAState>::new();
+        while let Some((state1, state2)) = waiting.pop() {
+            let from_state = get_state((state1, state2), &mut waiting);
+            if is_accept(
+                self.accepts.contains(&state1),
+                other.accepts.contains(&state2),
+            ) {
+                accepts.insert(from_state);
+            }
+            let chars = chars1
+                .get(&state1)
+                .into_iter()
+                .chain(chars2.get(&state2))
+                .flatten();
+            for char in chars {
+                let next1 = self.next_state(state1, *char);
+                let next2 = other.next_state(state2, *char);
+                if let (Some(next1), Some(next2)) = (next1, next2) {
+                    let to_state = get_state((next1, next2), &mut waiting);
+                    transition.insert((from_state, *char), to_state);
+                }
+            }
+            let next1 = self.default_transition.get(&state1);
+            let next2 = other.default_transition.get(&state2);
+            if let (Some(next1), Some(next2)) = (next1, next2) {
+                let to_state = get_state((*next1, *next2), &mut waiting);
+                default_transition.insert(from_state, to_state);
+            }
+        }
+
+        DeterministicFiniteAutomaton::new(
+            start,
+            state_map.len() as u32,
+            accepts,
+            transition,
+            default_transition,
+        )
+        .prune()
+    }
+
+    /// Removes the states that are unreachable from the start state or cannot reach any accept
+    /// state, and renumbers the rest in order. A transition into a removed state is dropped, except
+    /// that it has to lead to a dead state instead if it shadows a default transition.
+    fn prune(self) -> Self {
+        self.prune_with_state_map().0
+    }
+
+    /// Like `prune`, but also returns the new ids of the states that are kept.
+    fn prune_with_state_map(self) -> (Self, HashMap<DFAState, DFAState>) {
+        let mut keep = &self.reachable_states() & &self.productive_states();
+        keep.insert(self.start);
+        let mut keep = keep.into_iter().collect::<Vec<_>>();
+        keep.sort();
+        let state_map = keep
+            .iter()
+            .enumerate()
+            .map(|(i, state)| (*state, DFAState(i as u32)))
+            .collect::<HashMap<_, _>>();
+        let dead = DFAState(state_map.len() as u32);
+
+        let mut transition = HashMap::<(DFAState, char), DFAState>::new();
+        let mut default_transition = HashMap::<DFAState, DFAState>::new();
+        for (from, to) in &self.default_transition {
+            if let (Some(from), Some(to)) = (state_map.get(from), state_map.get(to)) {
+                default_transition.insert(*from, *to);
+            }
+        }
+        for ((from, char), to) in &self.transition {
+            let Some(from) = state_map.get(from) else {
+                continue;
+            };
+            match state_map.get(to) {
+                Some(to) => {
+                    transition.insert((*from, *char), *to);
+                }
+                None if default_transition.contains_key(from) => {
+                    transition.insert((*from, *char), dead);
+                }
+                None => {}
+            }
+        }
+
+        let has_dead = transition.values().any(|to| *to == dead);
+        let accepts = self
+            .accepts
+            .iter()
+            .filter_map(|state| state_map.get(state).cloned())
+            .collect();
+        let dfa = DeterministicFiniteAutomaton::new(
+            state_map[&self.start],
+            state_map.len() as u32 + has_dead as u32,
+            accepts,
+            transition,
+            default_transition,
+        );
+        (dfa, state_map)
+    }
+
+    /// Returns the equivalent automaton with the fewest states, merging the states that accept
+    /// the same strings.
+    pub fn minimize(&self) -> Self {
+        // block[state] = The id of the group of states that are not distinguished (yet).
+        // The groups are split until every state in a group transitions into the same groups.
+        let mut block = (0..self.state_count)
+            .map(|state| self.is_accept[state as usize] as usize)
+            .collect::<Vec<_>>();
+        let mut block_count = block.iter().collect::<HashSet<_>>().len();
+        loop {
+            let mut signatures = HashMap::<(usize, Vec<Option<usize>>), usize>::new();
+            let next_block = (0..self.state_count as usize)
+                .map(|state| {
+                    let row = self.row(state).iter();
+                    let row = row.map(|to| to.map(|to| block[to.0 as usize])).collect();
+                    let id = signatures.len();
+                    *signatures.entry((block[state], row)).or_insert(id)
+                })
+                .collect::<Vec<_>>();
+            block = next_block;
+            if signatures.len() == block_count {
+                break;
+            }
+            block_count = signatures.len();
+        }
+
+        let block_of = |state: &DFAState| DFAState(block[state.0 as usize] as u32);
+        let transition = self
+            .transition
+            .iter()
+            .map(|((from, char), to)| ((block_of(from), *char), block_of(to)))
+            .collect();
+        let default_transition = self
+            .default_transition
+            .iter()
+            .map(|(from, to)| (block_of(from), block_of(to)))
+            .collect();
+        DeterministicFiniteAutomaton::new(
+            block_of(&self.start),
+            block_count as u32,
+            self.accepts.iter().map(block_of).collect(),
+            transition,
+            default_transition,
+        )
+    }
+
+    /// Returns true if no accept state is reachable from the start state.
+    pub fn is_empty(&self) -> bool {
+        self.reachable_states().is_disjoint(&self.accepts)
+    }
+
+    /// Returns true if infinitely many strings are accepted, i.e. there is a cycle through states
+    /// that are reachable from the start state and can reach an accept state.
+    pub fn is_infinite(&self) -> bool {
+        let useful = &self.reachable_states() & &self.productive_states();
+        let successors = self.successors();
+        let next_useful = |state: DFAState| -> Vec<DFAState> {
+            successors
+                .get(&state)
+                .into_iter()
+                .flatten()
+                .filter(|s| useful.contains(*s))
+                .cloned()
+                .collect()
+        };
+
+        // finished[state] = false while `state` is on the DFS stack, true after it is left.
+        let mut finished = HashMap::<DFAState, bool>::new();
+        for root in &useful {
+            if finished.contains_key(root) {
+                continue;
+            }
+            finished.insert(*root, false);
+            let mut stack = vec![(*root, next_useful(*root))];
+            while let Some((state, next)) = stack.last_mut() {
+                let state = *state;
+                match next.pop() {
+                    Some(next) => match finished.get(&next) {
+                        Some(false) => return true,
+                        Some(true) => {}
+                        None => {
+                            finished.insert(next, false);
+                            stack.push((next, next_useful(next)));
+                        }
+                    },
+                    None => {
+                        finished.insert(state, true);
+                        stack.pop();
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    /// Returns the number of accepted strings of exactly `n` characters, or `u64::MAX` if there
+    /// are more. Every `char` counts as a character, so a default transition stands for all of
+    /// them but the ones with a transition of their own.
+    pub fn count_strings_of_length(&self, n: usize) -> u64 {
+        // The number of `char`s, which excludes the surrogates.
+        const CHAR_COUNT: u64 = 0x110000 - 0x800;
+        let chars = self.transition_chars();
+        // counts[state] = The number of strings of the current length that lead to `state`.
+        let mut counts = vec![0u64; self.state_count as usize];
+        counts[self.start.0 as usize] = 1;
+        for _ in 0..n {
+            let mut next_counts = vec![0u64; self.state_count as usize];
+            for ((from, _), to) in &self.transition {
+                let count = &mut next_counts[to.0 as usize];
+                *count = count.saturating_add(counts[from.0 as usize]);
+            }
+            for (from, to) in &self.default_transition {
+                let explicit = chars.get(from).map_or(0, |chars| chars.len() as u64);
+                let paths = counts[from.0 as usize].saturating_mul(CHAR_COUNT - explicit);
+                let count = &mut next_counts[to.0 as usize];
+                *count = count.saturating_add(paths);
+            }
+            counts = next_counts;
+        }
+        self.accepts.iter().fold(0, |sum, accept| {
+            sum.saturating_add(counts[accept.0 as usize])
+        })
+    }
+
+    /// Returns the shortest accepted string, preferring the lexicographically smallest one. A
+    /// character without a transition of its own is tried as the first of `a`-`z`, `A`-`Z` and
+    /// `0`-`9` that has none, so `b|\D` gives "a" rather than "\0".
+    pub fn shortest_string(&self) -> Option<String> {
+        let chars = self.transition_chars();
+        // parent[state] = The previous state and the character to reach `state`.
+        let mut parent = HashMap::<DFAState, Option<(DFAState, char)>>::new();
+        let mut queue = VecDeque::from([self.start]);
+        parent.insert(self.start, None);
+        while let Some(state) = queue.pop_front() {
+            if self.accepts.contains(&state) {
+                let mut ret = vec![];
+                let mut look = state;
+                while let Some((prev, char)) = parent[&look] {
+                    ret.push(char);
+                    look = prev;
+                }
+                return Some(ret.into_iter().rev().collect());
+            }
+            let mut edges = chars
+                .get(&state)
+                .into_iter()
+                .flatten()
+                .map(|c| (*c, self.transition[&(state, *c)]))
+                .collect::<Vec<_>>();
+            if let Some(to) = self.default_transition.get(&state) {
+                edges.push((self.default_char(&chars, state), *to));
+            }
+            edges.sort();
+            for (char, to) in edges {
+                if let Entry::Vacant(entry) = parent.entry(to) {
+                    entry.insert(Some((state, char)));
+                    queue.push_back(to);
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns the characters every accepted string starts with: the transitions taken from the
+    /// start state while a non-accepting state has exactly one character leading to an accept
+    /// state.
+    pub fn literal_prefix(&self) -> String {
+        let chars = self.alphabet.len();
+        let mut symbol_chars = vec!['\0'; chars + 1];
+        for (char, symbol) in &self.alphabet {
+            symbol_chars[*symbol] = *char;
+        }
+        let mut ret = String::new();
+        let mut state = self.start.0 as usize;
+        while !self.is_accept[state] && !self.is_dead[state] {
+            let mut live = self
+                .row(state)
+                .iter()
+                .enumerate()
+                .filter_map(|(symbol, to)| {
+                    Some((symbol, to.filter(|to| !self.is_dead[to.0 as usize])?))
+                });
+            match (live.next(), live.next()) {
+                (Some((symbol, to)), None) if symbol != 0 => {
+                    ret.push(symbol_chars[symbol]);
+                    state = to.0 as usize;
+                }
+                _ => break,
+            }
+        }
+        ret
+    }
+
+    /// Returns a random accepted string of at most `max_len` characters, or `None` if there is
+    /// none. At each state, stopping there if it accepts and each transition that can still reach
+    /// an accept state in time are equally likely.
+    #[cfg(feature = "rand")]
+    pub fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R, max_len: usize) -> Option<String> {
+        let distances = self.distances_to_accept();
+        let chars = self.transition_chars();
+        let reachable =
+            |state: &DFAState, len: usize| distances.get(state).is_some_and(|d| *d <= len);
+        if !reachable(&self.start, max_len) {
+            return None;
+        }
+
+        let mut ret = String::new();
+        let mut state = self.start;
+        for len in (0..max_len).rev() {
+            // `None` stands for the default transition.
+            let mut choices = chars
+                .get(&state)
+                .into_iter()
+                .flatten()
+                .map(|char| (Some(*char), self.transition[&(state, *char)]))
+                .chain(self.default_transition.get(&state).map(|to| (None, *to)))
+                .filter(|(_, to)| reachable(to, len))
+                .collect::<Vec<_>>();
+            let stop = self.accepts.contains(&state) as usize;
+            let choice = rng.random_range(0..choices.len() + stop);
+            if choice == choices.len() {
+                return Some(ret);
+            }
+            let (char, to) = choices.swap_remove(choice);
+            ret.push(char.unwrap_or_else(|| loop {
+                let char = rng.random::<char>();
+                if chars.get(&state).is_none_or(|chars| !chars.contains(&char)) {
+                    break char;
+                }
+            }));
+            state = to;
+        }
+        Some(ret)
+    }
+
+    /// distances_to_accept()[state] = The length of the shortest string leading from `state` to
+    /// an accept state. The states that can not reach any accept state are missing.
+    #[cfg(feature = "rand")]
+    fn distances_to_accept(&self) -> HashMap<DFAState, usize> {
+        let mut predecessors = HashMap::<DFAState, HashSet<DFAState>>::new();
+        for (from, to_states) in self.successors() {
+            for to in to_states {
+                predecessors.entry(to).or_default().insert(from);
+            }
+        }
+        let mut ret = self
+            .accepts
+            .iter()
+            .map(|accept| (*accept, 0))
+            .collect::<HashMap<_, _>>();
+        let mut queue = self.accepts.iter().cloned().collect::<VecDeque<_>>();
+        while let Some(state) = queue.pop_front() {
+            let distance = ret[&state] + 1;
+            for prev in predecessors.get(&state).into_iter().flatten() {
+                if let Entry::Vacant(entry) = ret.entry(*prev) {
+                    entry.insert(distance);
+                    queue.push_back(*prev);
+                }
+            }
+        }
+        ret
+    }
+
+    /// Returns a character that is transitioned by the default transition of `state`.
+    fn default_char(&self, chars: &HashMap<DFAState, BTreeSet<char>>, state: DFAState) -> char {
+        let explicit = chars.get(&state);
+        ('a'..='z')
+            .chain('A'..='Z')
+            .chain('0'..='9')
+            .chain('\0'..=char::MAX)
+            .find(|c| explicit.is_none_or(|chars| !chars.contains(c)))
+            .unwrap()
+    }
+
+    /// Returns the states reachable from the start state.
+    fn reachable_states(&self) -> HashSet<DFAState> {
+        let successors = self.successors();
+        let mut ret = HashSet::<DFAState>::new();
+        let mut stack = vec![self.start];
+        while let Some(state) = stack.pop() {
+            if ret.insert(state) {
+                stack.extend(successors.get(&state).into_iter().flatten());
+            }
+        }
+        ret
+    }
+
+    /// Returns the states from which some accept state is reachable.
+    fn productive_states(&self) -> HashSet<DFAState> {
+        (0..self.state_count)
+            .map(DFAState)
+            .filter(|state| !self.is_dead[state.0 as usize])
+            .collect()
+    }
+
+    /// dead_states()[state] = True if no accept state is reachable from `state`.
+    /// Computed over the dense table, as it runs for every automaton built.
+    fn dead_states(&self) -> Vec<bool> {
+        let mut predecessors = vec![vec![]; self.state_count as usize];
+        for from in 0..self.state_count as usize {
+            for to in self.row(from).iter().flatten() {
+                predecessors[to.0 as usize].push(from);
+            }
+        }
+        let mut ret = vec![true; self.state_count as usize];
+        let mut stack = self
+            .accepts
+            .iter()
+            .map(|state| state.0 as usize)
+            .collect::<Vec<_>>();
+        while let Some(state) = stack.pop() {
+            if core::mem::replace(&mut ret[state], false) {
+                stack.extend(&predecessors[state]);
+            }
+        }
+        ret
+    }
+
+    /// successors()[state] = The states that can be transitioned from `state` by some character.
+    fn successors(&self) -> HashMap<DFAState, HashSet<DFAState>> {
+        let mut ret = HashMap::<DFAState, HashSet<DFAState>>::new();
+        let transition = self.transition.iter().map(|((from, _), to)| (from, to));
+        for (from, to) in transition.chain(&self.default_transition) {
+            ret.entry(*from).or_default().insert(*to);
+        }
+        ret
+    }
+
+    /// transition_chars()[state] = The characters that have their own transition from `state`.
+    fn transition_chars(&self) -> HashMap<DFAState, BTreeSet<char>> {
+        let mut ret = HashMap::<DFAState, BTreeSet<char>>::new();
+        for (state, char) in self.transition.keys() {
+            ret.entry(*state).or_default().insert(*char);
+        }
+        ret
+    }
+
+    /// Returns the automaton in a binary format, which `deserialize` reads back.
+    ///
+    /// The format is the header `DFA` and the version `1`, followed by little-endian `u32`s:
+    /// the state count, the start state, the accept states, the transitions as
+    /// `(from, char, to)` and the default transitions as `(from, to)`, each list preceded by its
+    /// length.
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut accepts = self.accepts.iter().map(|state| state.0).collect::<Vec<_>>();
+        accepts.sort();
+        let mut transition = self.transitions().collect::<Vec<_>>();
+        transition.sort();
+        let mut default_transition = self.default_transitions().collect::<Vec<_>>();
+        default_transition.sort();
+
+        let mut values = vec![self.state_count, self.start.0, accepts.len() as u32];
+        values.extend(accepts);
+        values.push(transition.len() as u32);
+        for (from, char, to) in transition {
+            values.extend([from.0, char as u32, to.0]);
+        }
+        values.push(default_transition.len() as u32);
+        for (from, to) in default_transition {
+            values.extend([from.0, to.0]);
+        }
+
+        let mut ret = SERIALIZE_HEADER.to_vec();
+        ret.extend(values.into_iter().flat_map(u32::to_le_bytes));
+        ret
+    }
+
+    /// Reads an automaton written by `serialize`.
+    pub fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if !bytes.starts_with(SERIALIZE_HEADER) {
+            return Err(DeserializeError::InvalidHeader { offset: 0 });
+        }
+        let mut reader = Reader {
+            bytes,
+            position: SERIALIZE_HEADER.len(),
+        };
+
+        // The dense table has a row for each state, so the count is checked before it is built.
+        let offset = reader.position;
+        let state_count = reader.read_u32()?;
+        if state_count as usize > 1 + (bytes.len() - reader.position) / 4 {
+            return Err(DeserializeError::TooManyStates {
+                count: state_count,
+                offset,
+            });
+        }
+        let start = reader.read_state(state_count)?;
+        let mut accepts = HashSet::<DFAState>::new();
+        for _ in 0..reader.read_u32()? {
+            accepts.insert(reader.read_state(state_count)?);
+        }
+        let mut transition = HashMap::<(DFAState, char), DFAState>::new();
+        for _ in 0..reader.read_u32()? {
+            let from = reader.read_state(state_count)?;
+            let char = reader.read_char()?;
+            transition.insert((from, char), reader.read_state(state_count)?);
+        }
+        let mut default_transition = HashMap::<DFAState, DFAState>::new();
+        for _ in 0..reader.read_u32()? {
+            let from = reader.read_state(state_count)?;
+            default_transition.insert(from, reader.read_state(state_count)?);
+        }
+        if reader.position != bytes.len() {
+            return Err(DeserializeError::TrailingBytes {
+                offset: reader.position,
+            });
+        }
+
+        Ok(DeterministicFiniteAutomaton::new(
+            start,
+            state_count,
+            accepts,
+            transition,
+            default_transition,
+        ))
+    }
+
+    /// Reads an automaton written by `to_table`, e.g. by hand. The rows can be in any order, but
+    /// every state must be less than the state count, and a state can not have two transitions
+    /// by the same character or two default transitions. Every state up to the count must be in
+    /// the table, so that a large count can not make the automaton larger than the table.
+    pub fn from_table(text: &str) -> Result<Self, TableError> {
+        let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line));
+        let mut header = |expected: &'static str| match lines.next() {
+            Some((line, text)) => text
+                .strip_prefix(expected)
+                .map(|rest| (line, rest.trim()))
+                .ok_or(TableError::MissingHeader { expected, line }),
+            None => Err(TableError::MissingHeader {
+                expected,
+                line: text.lines().count() + 1,
+            }),
+        };
+        let (count_line, state_count) = header("# states:")?;
+        let state_count = state_count
+            .parse::<u32>()
+            .map_err(|_| TableError::InvalidRow { line: count_line })?;
+        let state = |id: &str, line: usize| match id.parse::<u32>() {
+            Ok(id) if id < state_count => Ok(DFAState(id)),
+            Ok(id) => Err(TableError::InvalidState { id, line }),
+            Err(_) => Err(TableError::InvalidRow { line }),
+        };
+        let (line, start) = header("# start:")?;
+        let start = state(start, line)?;
+        let (line, accepts) = header("# accepts:")?;
+        let accepts = accepts
+            .split_whitespace()
+            .map(|accept| state(accept, line))
+            .collect::<Result<HashSet<_>, _>>()?;
+        header("from,char,to")?;
+
+        // The dense table is built for `state_count` states, so they must all be in the table.
+        let mut mentioned = accepts.clone();
+        mentioned.insert(start);
+        let mut transition = HashMap::<(DFAState, char), DFAState>::new();
+        let mut default_transition = HashMap::<DFAState, DFAState>::new();
+        for (line, row) in lines {
+            // Neither state contains a `,`, so the first and the last one delimit the character.
+            let (from, char, to) = row
+                .split_once(',')
+                .and_then(|(from, rest)| Some((from, rest.rsplit_once(',')?)))
+                .and_then(|(from, (char, to))| Some((from, unescape_table_char(char)?, to)))
+                .ok_or(TableError::InvalidRow { line })?;
+            let (from, to) = (state(from, line)?, state(to, line)?);
+            mentioned.extend([from, to]);
+            let duplicate = match char {
+                Some(char) => transition.insert((from, char), to).is_some(),
+                None => default_transition.insert(from, to).is_some(),
+            };
+            if duplicate {
+                return Err(TableError::DuplicateTransition { line });
+            }
+        }
+        if mentioned.len() != state_count as usize {
+            return Err(TableError::UnusedStates {
+                count: state_count,
+                line: count_line,
+            });
+        }
+
+        Ok(DeterministicFiniteAutomaton::new(
+            start,
+            state_count,
+            accepts,
+            transition,
+            default_transition,
+        ))
+    }
+
+    /// Returns the automaton in the Graphviz DOT format.
+    /// Default transitions are drawn as dashed edges labeled "other".
+    pub fn to_dot(&self) -> String {
+        let mut ret = String::new();
+        ret.push_str("digraph dfa {\n");
+        ret.push_str("    rankdir = LR;\n");
+        ret.push_str("    node [shape = circle];\n");
+        ret.push_str("    start [shape = point];\n");
+        ret.push_str(&format!("    start -> {};\n", self.start.0));
+
+        let mut accepts = self.accepts.iter().collect::<Vec<_>>();
+        accepts.sort();
+        for accept in accepts {
+            ret.push_str(&format!("    {} [shape = doublecircle];\n", accept.0));
+        }
+
+        let mut transition = self.transition.iter().collect::<Vec<_>>();
+        transition.sort();
+        for ((from, char), to) in transition {
+            ret.push_str(&format!(
+                "    {} -> {} [label = \"{}\"];\n",
+                from.0,
+                to.0,
+                escape_dot_label(*char)
+            ));
+        }
+
+        let mut default_transition = self.default_transition.iter().collect::<Vec<_>>();
+        default_transition.sort();
+        for (from, to) in default_transition {
+            ret.push_str(&format!(
+                "    {} -> {} [label = \"other\", style = dashed];\n",
+                from.0, to.0
+            ));
+        }
+
+        ret.push_str("}\n");
+        ret
+    }
+
+    /// Returns the transitions as comma-separated `from,char,to` rows under a header, preceded by
+    /// `#` lines with the state count, the start state and the accept states. A default
+    /// transition has an empty `char`. A `,` and `\` are escaped as `\,` and `\\`, and whitespace
+    /// and control characters as `\u{...}`.
+    pub fn to_table(&self) -> String {
+        let mut accepts = self.accepts.iter().collect::<Vec<_>>();
+        accepts.sort();
+        let accepts = accepts
+            .into_iter()
+            .map(|accept| accept.0.to_string())
+            .collect::<Vec<_>>();
+        let mut ret = String::new();
+        ret.push_str(&format!("# states: {}\n", self.state_count));
+        ret.push_str(&format!("# start: {}\n", self.start.0));
+        ret.push_str(&format!("# accepts: {}\n", accepts.join(" ")));
+        ret.push_str("from,char,to\n");
+
+        let mut rows = self
+            .transition
+            .iter()
+            .map(|((from, char), to)| (*from, Some(*char), *to))
+            .chain(
+                self.default_transition
+                    .iter()
+                    .map(|(from, to)| (*from, None, *to)),
+            )
+            .collect::<Vec<_>>();
+        // Sorts the default transition of a state after its other ones.
+        rows.sort_by_key(|(from, char, to)| (*from, char.is_none(), *char, *to));
+        for (from, char, to) in rows {
+            let char = char.map(escape_table_char).unwrap_or_default();
+            ret.push_str(&format!("{},{},{}\n", from.0, char, to.0));
+        }
+        ret
     }
 }
 
+impl Automaton for DeterministicFiniteAutomaton {
+    type State = DFAState;
+
+    fn start_state(&self) -> DFAState {
+        self.start
+    }
+
+    fn step(&self, state: &DFAState, char: char) -> Option<DFAState> {
+        self.next_state(*state, char)
+            .filter(|state| !self.is_dead[state.0 as usize])
+    }
+
+    fn is_accept(&self, state: &DFAState) -> bool {
+        self.is_accept[state.0 as usize]
+    }
+}
+
+const SERIALIZE_HEADER: &[u8] = b"DFA\x01";
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn read_u32(&mut self) -> Result<u32, DeserializeError> {
+        let bytes = self.bytes.get(self.position..self.position + 4).ok_or(
+            DeserializeError::UnexpectedEnd {
+                offset: self.bytes.len(),
+            },
+        )?;
+        self.position += 4;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn read_state(&mut self, state_count: u32) -> Result<DFAState, DeserializeError> {
+        let offset = self.position;
+        match self.read_u32()? {
+            id if id < state_count => Ok(DFAState(id)),
+            id => Err(DeserializeError::InvalidState { id, offset }),
+        }
+    }
+
+    fn read_char(&mut self) -> Result<char, DeserializeError> {
+        let offset = self.position;
+        let value = self.read_u32()?;
+        char::from_u32(value).ok_or(DeserializeError::InvalidChar { value, offset })
+    }
+}
+
+fn escape_dot_label(char: char) -> String {
+    match char {
+        '"' => "\\\"".to_string(),
+        '\\' => "\\\\".to_string(),
+        // Escape control characters twice so that Graphviz shows e.g. `\n` instead of a line break.
+        char if char.is_control() => char.escape_default().to_string().replace('\\', "\\\\"),
+        char => char.to_string(),
+    }
+}
+
+fn escape_table_char(char: char) -> String {
+    match char {
+        ',' => "\\,".to_string(),
+        '\\' => "\\\\".to_string(),
+        char if char.is_whitespace() || char.is_control() => char.escape_unicode().to_string(),
+        char => char.to_string(),
+    }
+}
+
+/// Reads a character written by `escape_table_char`, or `None` for the empty default character.
+/// Returns `Some(None)` for the latter and `None` if `text` is not a single character.
+fn unescape_table_char(text: &str) -> Option<Option<char>> {
+    let char = match text {
+        "" => return Some(None),
+        "\\," => ',',
+        "\\\\" => '\\',
+        text => match text
+            .strip_prefix("\\u{")
+            .and_then(|hex| hex.strip_suffix('}'))
+        {
+            Some(hex) => char::from_u32(u32::from_str_radix(hex, 16).ok()?)?,
+            None => {
+                let mut chars = text.chars();
+                let char = chars.next()?;
+                if chars.next().is_some() || char == ',' || char == '\\' {
+                    return None;
+                }
+                char
+            }
+        },
+    };
+    Some(Some(char))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
 
     #[test]
     #[rustfmt::skip]
@@ -147,6 +1173,44 @@ mod tests {
         assert_eq!(context.get_state(&[NFAState(4)]),                           DFAState(4));
     }
 
+    #[test]
+    #[should_panic(expected = "too many states")]
+    fn dfa_context_overflow() {
+        let mut context = Context::new();
+        context.state_count = u32::MAX - 1;
+        assert_eq!(context.get_state(&[NFAState(0)]), DFAState(u32::MAX - 1));
+        assert_eq!(context.get_state(&[NFAState(0)]), DFAState(u32::MAX - 1));
+        context.get_state(&[NFAState(1)]);
+    }
+
+    #[test]
+    fn dfa_from_nested_nfa() {
+        let compile = |pattern| {
+            let node = Parser::new(Lexer::new(pattern)).unwrap().parse().unwrap();
+            DeterministicFiniteAutomaton::from_nfa(NondeterministicFiniteAutomaton::from_node(node))
+        };
+        let pattern = format!("{}a|(|b)*{}", "(".repeat(50), ")*".repeat(50));
+        let dfa = compile(&pattern);
+        assert!(dfa.equivalent(&compile(r"(a|b)*")));
+        assert_eq!(dfa.minimize().state_count, 1);
+    }
+
+    #[test]
+    fn dfa_from_nfa_deterministic() {
+        let compile = || {
+            let node = Parser::new(Lexer::new(r"(p(erl|ython|hp)|ruby)\W*\d.?"))
+                .unwrap()
+                .parse()
+                .unwrap();
+            DeterministicFiniteAutomaton::from_nfa(NondeterministicFiniteAutomaton::from_node(node))
+        };
+        let (dfa1, dfa2) = (compile(), compile());
+        assert_eq!(dfa1.state_count, dfa2.state_count);
+        assert_eq!(dfa1.accepts, dfa2.accepts);
+        assert_eq!(dfa1.transition, dfa2.transition);
+        assert_eq!(dfa1.default_transition, dfa2.default_transition);
+    }
+
     #[test]
     fn dfa_from_nfa_simple() {
         // -> 0 --a--> 1
@@ -160,6 +1224,7 @@ mod tests {
         // -> 0 --a--> 1
         // accept: 1
         assert_eq!(dfa.start, DFAState(0));
+        assert_eq!(dfa.state_count, 2);
         assert_eq!(dfa.accepts, [DFAState(1)].into());
         assert_eq!(dfa.transition.len(), 1);
         assert_eq!(dfa.transition[&(DFAState(0), 'a')], DFAState(1));
@@ -237,6 +1302,400 @@ mod tests {
         assert_eq!(dfa.transition[&(DFAState(1), 'a')], DFAState(1));
     }
 
+    #[test]
+    fn dfa_from_nfa_any() {
+        // -> 0 --any--> 1 --a--> 2
+        // accept: 2
+        let dfa = DeterministicFiniteAutomaton::from_nfa(
+            NondeterministicFiniteAutomaton::new(NFAState(0), [NFAState(2)].into())
+                .add_any_transition(NFAState(0), NFAState(1))
+                .add_transition(NFAState(1), 'a', NFAState(2)),
+        );
+
+        // -> 0 --any--> 1 --a--> 2
+        // accept: 2
+        assert_eq!(dfa.start, DFAState(0));
+        assert_eq!(dfa.accepts, [DFAState(2)].into());
+        assert_eq!(dfa.transition.len(), 1);
+        assert_eq!(dfa.default_transition.len(), 1);
+        assert_eq!(dfa.default_transition[&DFAState(0)], DFAState(1));
+        assert_eq!(dfa.transition[&(DFAState(1), 'a')], DFAState(2));
+        assert_eq!(dfa.next_state(DFAState(0), '猫'), Some(DFAState(1)));
+        assert_eq!(dfa.next_state(DFAState(1), '猫'), None);
+    }
+
+    #[test]
+    fn dfa_from_nfa_any_except() {
+        // -> 0 --any but a--> 1
+        // accept: 1
+        let dfa = DeterministicFiniteAutomaton::from_nfa(
+            NondeterministicFiniteAutomaton::new(NFAState(0), [NFAState(1)].into())
+                .add_any_except_transition(NFAState(0), ['a'].into(), NFAState(1)),
+        );
+
+        // -> 0 --a--> 1 (dead)
+        //     \--any--> 2
+        // accept: 2
+        assert_eq!(dfa.start, DFAState(0));
+        assert_eq!(dfa.transition.len(), 1);
+        assert_eq!(dfa.default_transition.len(), 1);
+        let dead = dfa.transition[&(DFAState(0), 'a')];
+        let accept = dfa.default_transition[&DFAState(0)];
+        assert_ne!(dead, accept);
+        assert_eq!(dfa.accepts, [accept].into());
+        assert_eq!(dfa.next_state(dead, 'b'), None);
+    }
+
+    #[test]
+    fn dfa_complete() {
+        let node = Parser::new(Lexer::new(r"a(b|\D)"))
+            .unwrap()
+            .parse()
+            .unwrap();
+        let dfa = DeterministicFiniteAutomaton::from_nfa(
+            NondeterministicFiniteAutomaton::from_node(node),
+        );
+        let alphabet = ['a', 'b', 'c', '0'].into();
+        let completed = dfa.clone().complete(&alphabet);
+        assert_eq!(completed.state_count, dfa.state_count + 1);
+        for state in (0..completed.state_count).map(DFAState) {
+            for char in &alphabet {
+                assert!(completed.next_state(state, *char).is_some());
+            }
+        }
+        assert!(completed.equivalent(&dfa));
+        let completed_again = completed.clone().complete(&alphabet);
+        assert_eq!(completed_again.state_count, completed.state_count);
+    }
+
+    #[test]
+    fn dfa_complement() {
+        // -> 0 --a--> 1
+        // accept: 1
+        let dfa =
+            DeterministicFiniteAutomaton::from_nfa(
+                NondeterministicFiniteAutomaton::new(NFAState(0), [NFAState(1)].into())
+                    .add_transition(NFAState(0), 'a', NFAState(1)),
+            )
+            .complement();
+
+        // -> 0 --a--> 1
+        // 0, 1, 2 --other--> 2 (sink)
+        // accept: 0, 2
+        assert_eq!(dfa.start, DFAState(0));
+        assert_eq!(dfa.state_count, 3);
+        assert_eq!(dfa.accepts, [DFAState(0), DFAState(2)].into());
+        assert_eq!(dfa.transition.len(), 1);
+        assert_eq!(dfa.transition[&(DFAState(0), 'a')], DFAState(1));
+        assert_eq!(
+            dfa.default_transition,
+            [
+                (DFAState(0), DFAState(2)),
+                (DFAState(1), DFAState(2)),
+                (DFAState(2), DFAState(2))
+            ]
+            .into()
+        );
+    }
+
+    #[test]
+    fn dfa_intersect() {
+        // -> 0 --a,b--> 1
+        // accept: 1
+        let dfa1 = DeterministicFiniteAutomaton::from_nfa(
+            NondeterministicFiniteAutomaton::new(NFAState(0), [NFAState(1)].into())
+                .add_transition(NFAState(0), 'a', NFAState(1))
+                .add_transition(NFAState(0), 'b', NFAState(1)),
+        );
+        // -> 0 --any--> 1
+        // accept: 1
+        let dfa2 = DeterministicFiniteAutomaton::from_nfa(
+            NondeterministicFiniteAutomaton::new(NFAState(0), [NFAState(1)].into())
+                .add_any_transition(NFAState(0), NFAState(1)),
+        );
+        let dfa = dfa1.intersect(&dfa2);
+
+        // -> 0 --a,b--> 1
+        // accept: 1
+        assert_eq!(dfa.start, DFAState(0));
+        assert_eq!(dfa.state_count, 2);
+        assert_eq!(dfa.accepts, [DFAState(1)].into());
+        assert_eq!(dfa.transition.len(), 2);
+        assert_eq!(dfa.transition[&(DFAState(0), 'a')], DFAState(1));
+        assert_eq!(dfa.transition[&(DFAState(0), 'b')], DFAState(1));
+        assert!(dfa.default_transition.is_empty());
+    }
+
+    #[test]
+    fn dfa_minimize() {
+        let compile = |pattern| {
+            let node = Parser::new(Lexer::new(pattern)).unwrap().parse().unwrap();
+            DeterministicFiniteAutomaton::from_nfa(NondeterministicFiniteAutomaton::from_node(node))
+        };
+
+        let dfa = compile(r"a(b|c)");
+        assert_eq!(dfa.state_count, 4);
+        let minimized = dfa.minimize();
+        assert_eq!(minimized.state_count, 3);
+        assert!(minimized.equivalent(&dfa));
+
+        // Every state of (a|b)*a*b* accepts, and `a` and `b` are the only characters.
+        let minimized = compile(r"(a|b)*a*b*").minimize();
+        assert_eq!(minimized.state_count, 1);
+        assert!(minimized.equivalent(&compile(r"(a|b)*")));
+
+        // The dead state for the digits stays distinct from the accept state.
+        let dfa = compile(r"\D*");
+        assert_eq!(dfa.minimize().state_count, 2);
+        assert!(dfa.minimize().equivalent(&dfa));
+    }
+
+    #[test]
+    fn dfa_is_empty() {
+        // -> 0 --a--> 1    2
+        // accept: 2
+        let dfa =
+            DeterministicFiniteAutomaton::from_nfa(
+                NondeterministicFiniteAutomaton::new(NFAState(0), [NFAState(2)].into())
+                    .add_transition(NFAState(0), 'a', NFAState(1)),
+            );
+        assert!(dfa.is_empty());
+        assert!(!dfa.complement().is_empty());
+    }
+
+    #[test]
+    fn dfa_is_infinite() {
+        // -> 0 --a--> 1 --b--> 2 (dead) --a--> 2
+        //     \--b--> 3
+        // accept: 1, 3
+        let dfa = DeterministicFiniteAutomaton::from_nfa(
+            NondeterministicFiniteAutomaton::new(NFAState(0), [NFAState(1), NFAState(3)].into())
+                .add_transition(NFAState(0), 'a', NFAState(1))
+                .add_transition(NFAState(1), 'b', NFAState(2))
+                .add_transition(NFAState(2), 'a', NFAState(2))
+                .add_transition(NFAState(0), 'b', NFAState(3)),
+        );
+        assert!(!dfa.is_infinite());
+
+        // -> 0 --a--> 1 --b--> 0
+        // accept: 1
+        let dfa = DeterministicFiniteAutomaton::from_nfa(
+            NondeterministicFiniteAutomaton::new(NFAState(0), [NFAState(1)].into())
+                .add_transition(NFAState(0), 'a', NFAState(1))
+                .add_transition(NFAState(1), 'b', NFAState(0)),
+        );
+        assert!(dfa.is_infinite());
+    }
+
+    #[test]
+    fn dfa_shortest_string() {
+        // -> 0 --b--> 1 --any--> 2
+        //     \--a--> 3 --a--> 4 --a--> 2
+        // accept: 2
+        let dfa = DeterministicFiniteAutomaton::from_nfa(
+            NondeterministicFiniteAutomaton::new(NFAState(0), [NFAState(2)].into())
+                .add_transition(NFAState(0), 'b', NFAState(1))
+                .add_any_transition(NFAState(1), NFAState(2))
+                .add_transition(NFAState(0), 'a', NFAState(3))
+                .add_transition(NFAState(3), 'a', NFAState(4))
+                .add_transition(NFAState(4), 'a', NFAState(2)),
+        );
+        assert_eq!(dfa.shortest_string(), Some("ba".to_string()));
+        assert_eq!(dfa.complement().shortest_string(), Some("".to_string()));
+        assert_eq!(dfa.intersect(&dfa.complement()).shortest_string(), None);
+    }
+
+    #[test]
+    fn dfa_serialize() {
+        let node = Parser::new(Lexer::new(r"(a|b)*c\D"))
+            .unwrap()
+            .parse()
+            .unwrap();
+        let dfa = DeterministicFiniteAutomaton::from_nfa(
+            NondeterministicFiniteAutomaton::from_node(node),
+        );
+        let bytes = dfa.serialize();
+        assert!(bytes.starts_with(b"DFA\x01"));
+        let deserialized = DeterministicFiniteAutomaton::deserialize(&bytes).unwrap();
+        assert_eq!(deserialized.start, dfa.start);
+        assert_eq!(deserialized.state_count, dfa.state_count);
+        assert_eq!(deserialized.accepts, dfa.accepts);
+        assert_eq!(deserialized.transition, dfa.transition);
+        assert_eq!(deserialized.default_transition, dfa.default_transition);
+        assert_eq!(deserialized.serialize(), bytes);
+    }
+
+    #[test]
+    fn dfa_deserialize_error() {
+        let deserialize = DeterministicFiniteAutomaton::deserialize;
+        assert_eq!(
+            deserialize(b"NFA\x01").err(),
+            Some(DeserializeError::InvalidHeader { offset: 0 })
+        );
+        assert_eq!(
+            deserialize(b"DFA\x01\x01\0\0").err(),
+            Some(DeserializeError::UnexpectedEnd { offset: 7 })
+        );
+        let mut bytes = b"DFA\x01".to_vec();
+        bytes.extend([1, 0, 0, 0, 1, 0, 0, 0]);
+        assert_eq!(
+            deserialize(&bytes).err(),
+            Some(DeserializeError::InvalidState { id: 1, offset: 8 })
+        );
+        let mut bytes = DeterministicFiniteAutomaton::from_nfa(
+            NondeterministicFiniteAutomaton::new(NFAState(0), [NFAState(0)].into()),
+        )
+        .serialize();
+        bytes.push(0);
+        assert_eq!(
+            deserialize(&bytes).err(),
+            Some(DeserializeError::TrailingBytes { offset: 28 })
+        );
+        let mut bytes = b"DFA\x01".to_vec();
+        bytes.extend([0xFE, 0xFF, 0xFF, 0xFF]);
+        bytes.extend([0; 16]);
+        assert_eq!(
+            deserialize(&bytes).err(),
+            Some(DeserializeError::TooManyStates {
+                count: 0xFFFFFFFE,
+                offset: 4
+            })
+        );
+    }
+
+    #[test]
+    fn dfa_to_table() {
+        // -> 0 --,--> 1 --any--> 2 --a--> 2
+        //                            \--\n--> 2
+        // accept: 2
+        let dfa = DeterministicFiniteAutomaton::from_nfa(
+            NondeterministicFiniteAutomaton::new(NFAState(0), [NFAState(2)].into())
+                .add_transition(NFAState(0), ',', NFAState(1))
+                .add_any_transition(NFAState(1), NFAState(2))
+                .add_transition(NFAState(2), 'a', NFAState(2))
+                .add_transition(NFAState(2), '\n', NFAState(2)),
+        );
+        assert_eq!(
+            dfa.to_table(),
+            [
+                "# states: 3",
+                "# start: 0",
+                "# accepts: 2",
+                "from,char,to",
+                "0,\\,,1",
+                "1,,2",
+                "2,\\u{a},2",
+                "2,a,2\n",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn dfa_from_table() {
+        let dfa = DeterministicFiniteAutomaton::from_nfa(
+            NondeterministicFiniteAutomaton::new(NFAState(0), [NFAState(2)].into())
+                .add_transition(NFAState(0), ',', NFAState(1))
+                .add_any_transition(NFAState(1), NFAState(2))
+                .add_transition(NFAState(2), '\\', NFAState(2))
+                .add_transition(NFAState(2), '\n', NFAState(2)),
+        );
+        let table = dfa.to_table();
+        let read = DeterministicFiniteAutomaton::from_table(&table).unwrap();
+        assert_eq!(read.to_table(), table);
+        assert!(read.equivalent(&dfa));
+
+        let table = |rows: &[&str]| {
+            let mut table =
+                ["# states: 2", "# start: 0", "# accepts: 1", "from,char,to"].join("\n");
+            for row in rows {
+                table.push('\n');
+                table.push_str(row);
+            }
+            DeterministicFiniteAutomaton::from_table(&table)
+        };
+        assert!(table(&["0,a,1", "1,,1"]).is_ok());
+        assert_eq!(
+            table(&["0,a,2"]).err(),
+            Some(TableError::InvalidState { id: 2, line: 5 })
+        );
+        assert_eq!(
+            table(&["0,ab,1"]).err(),
+            Some(TableError::InvalidRow { line: 5 })
+        );
+        assert_eq!(
+            table(&["0,a"]).err(),
+            Some(TableError::InvalidRow { line: 5 })
+        );
+        assert_eq!(
+            table(&["0,a,1", "0,a,0"]).err(),
+            Some(TableError::DuplicateTransition { line: 6 })
+        );
+        assert_eq!(
+            DeterministicFiniteAutomaton::from_table("# states: 2\n# start: 2\n").err(),
+            Some(TableError::InvalidState { id: 2, line: 2 })
+        );
+        assert_eq!(
+            DeterministicFiniteAutomaton::from_table(
+                "# states: 4294967294\n# start: 0\n# accepts:\nfrom,char,to\n"
+            )
+            .err(),
+            Some(TableError::UnusedStates {
+                count: 4294967294,
+                line: 1
+            })
+        );
+        assert_eq!(
+            DeterministicFiniteAutomaton::from_table(
+                "# states: 3\n# start: 0\n# accepts: 1\nfrom,char,to\n0,a,1"
+            )
+            .err(),
+            Some(TableError::UnusedStates { count: 3, line: 1 })
+        );
+        assert_eq!(
+            DeterministicFiniteAutomaton::from_table("# states: 1\n# start: 0\n").err(),
+            Some(TableError::MissingHeader {
+                expected: "# accepts:",
+                line: 3
+            })
+        );
+    }
+
+    #[test]
+    fn dfa_to_dot() {
+        // -> 0 --"--> 1 --any--> 2
+        // accept: 2
+        let dfa = DeterministicFiniteAutomaton::from_nfa(
+            NondeterministicFiniteAutomaton::new(NFAState(0), [NFAState(2)].into())
+                .add_transition(NFAState(0), '"', NFAState(1))
+                .add_any_transition(NFAState(1), NFAState(2)),
+        );
+        assert_eq!(
+            dfa.to_dot(),
+            [
+                "digraph dfa {",
+                "    rankdir = LR;",
+                "    node [shape = circle];",
+                "    start [shape = point];",
+                "    start -> 0;",
+                "    2 [shape = doublecircle];",
+                "    0 -> 1 [label = \"\\\"\"];",
+                "    1 -> 2 [label = \"other\", style = dashed];",
+                "}\n",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn dot_label() {
+        assert_eq!(escape_dot_label('a'), "a");
+        assert_eq!(escape_dot_label('猫'), "猫");
+        assert_eq!(escape_dot_label('"'), r#"\""#);
+        assert_eq!(escape_dot_label('\\'), r"\\");
+        assert_eq!(escape_dot_label('\n'), r"\\n");
+    }
+
     #[test]
     fn dfa_from_nfa_complex() {
         // -> 0 --x--> 1
@@ -0,0 +1,138 @@
+use crate::automaton::dfa::DFAState;
+use crate::automaton::nfa::{NFAState, NondeterministicFiniteAutomaton};
+use crate::automaton::{state_id, Automaton};
+use crate::prelude::*;
+#[cfg(feature = "no_std")]
+use spin::{Mutex, MutexGuard};
+#[cfg(not(feature = "no_std"))]
+use std::sync::{Mutex, MutexGuard};
+
+/// The part of the DFA that has been built so far.
+#[derive(Clone, Default)]
+struct Cache {
+    /// states[id] = The sorted set of NFA states the DFA state `id` stands for.
+    states: Vec<Vec<NFAState>>,
+    state_map: HashMap<Vec<NFAState>, DFAState>,
+    /// `None` means no state can be transitioned by the character.
+    transition: HashMap<(DFAState, char), Option<DFAState>>,
+}
+
+impl Cache {
+    fn get_state(&mut self, mut states: Vec<NFAState>) -> DFAState {
+        states.sort();
+        if let Some(state) = self.state_map.get(&states) {
+            return *state;
+        }
+        let state = DFAState(state_id(self.states.len()));
+        self.states.push(states.clone());
+        self.state_map.insert(states, state);
+        state
+    }
+}
+
+/// A DFA whose states and transitions are computed from the NFA the first time they are visited.
+pub struct LazyDeterministicFiniteAutomaton {
+    nfa: NondeterministicFiniteAutomaton,
+    cache: Mutex<Cache>,
+}
+
+impl LazyDeterministicFiniteAutomaton {
+    pub fn from_nfa(nfa: NondeterministicFiniteAutomaton) -> Self {
+        LazyDeterministicFiniteAutomaton {
+            nfa,
+            cache: Mutex::new(Cache::default()),
+        }
+    }
+
+    pub fn nfa(&self) -> &NondeterministicFiniteAutomaton {
+        &self.nfa
+    }
+
+    fn cache(&self) -> MutexGuard<'_, Cache> {
+        #[cfg(feature = "no_std")]
+        return self.cache.lock();
+        #[cfg(not(feature = "no_std"))]
+        return self.cache.lock().unwrap();
+    }
+}
+
+/// The clone starts with the part of the DFA built so far, and builds the rest on its own.
+impl Clone for LazyDeterministicFiniteAutomaton {
+    fn clone(&self) -> Self {
+        LazyDeterministicFiniteAutomaton {
+            nfa: self.nfa.clone(),
+            cache: Mutex::new(self.cache().clone()),
+        }
+    }
+}
+
+impl Automaton for LazyDeterministicFiniteAutomaton {
+    type State = DFAState;
+
+    fn start_state(&self) -> DFAState {
+        let start_states = self.nfa.start_state();
+        self.cache().get_state(start_states)
+    }
+
+    fn step(&self, state: &DFAState, char: char) -> Option<DFAState> {
+        let mut cache = self.cache();
+        if let Some(next_state) = cache.transition.get(&(*state, char)) {
+            return *next_state;
+        }
+        let states = &cache.states[state.0 as usize];
+        let next_state = self
+            .nfa
+            .step(states, char)
+            .map(|next_states| cache.get_state(next_states));
+        cache.transition.insert((*state, char), next_state);
+        next_state
+    }
+
+    fn is_accept(&self, state: &DFAState) -> bool {
+        let cache = self.cache();
+        self.nfa.is_accept(&cache.states[state.0 as usize])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::automaton::dfa::DeterministicFiniteAutomaton;
+    use crate::parser::Node;
+
+    #[test]
+    fn materialize_visited_states_only() {
+        // (a|b)*a(a|b)(a|b)(a|b)
+        let any = || {
+            Node::Union(
+                Box::new(Node::Character('a')),
+                Box::new(Node::Character('b')),
+            )
+        };
+        let mut node = Node::Concat(
+            Box::new(Node::Star(Box::new(any()))),
+            Box::new(Node::Character('a')),
+        );
+        for _ in 0..3 {
+            node = Node::Concat(Box::new(node), Box::new(any()));
+        }
+        let lazy = LazyDeterministicFiniteAutomaton::from_nfa(
+            NondeterministicFiniteAutomaton::from_node(node),
+        );
+
+        let mut state = lazy.start_state();
+        for char in "bb".chars() {
+            state = lazy.step(&state, char).unwrap();
+        }
+        assert!(!lazy.is_accept(&state));
+        assert_eq!(lazy.cache().states.len(), 2);
+
+        let state = lazy.step(&state, 'a').unwrap();
+        assert_eq!(lazy.step(&state, 'b'), lazy.step(&state, 'b'));
+        assert_eq!(lazy.cache().states.len(), 4);
+        assert_eq!(lazy.step(&state, 'c'), None);
+
+        let dfa = DeterministicFiniteAutomaton::from_nfa(lazy.nfa().clone());
+        assert!(dfa.state_count() >= 16);
+    }
+}
@@ -1,103 +1,587 @@
-use crate::parser::Node;
-use std::collections::{HashMap, HashSet};
+use crate::automaton::dfa::{DFAState, DeterministicFiniteAutomaton};
+use crate::automaton::{state_id, Automaton};
+use crate::parser::{drop_tree, Node};
+use crate::prelude::*;
+use alloc::collections::BTreeSet;
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
 pub struct NFAState(pub u32);
 
 struct Context {
     state_count: u32,
+    case_insensitive: bool,
+    dot_matches_newline: bool,
 }
 
 impl Context {
-    fn new() -> Self {
-        Context { state_count: 0 }
+    fn new(case_insensitive: bool, dot_matches_newline: bool) -> Self {
+        Context {
+            state_count: 0,
+            case_insensitive,
+            dot_matches_newline,
+        }
     }
 
     fn new_state(&mut self) -> NFAState {
-        let id = self.state_count;
-        self.state_count += 1;
+        let id = state_id(self.state_count as usize);
+        self.state_count = id + 1;
         NFAState(id)
     }
+
+    /// Returns the characters `char` matches, which are its case variants if case-insensitive.
+    fn variants(&self, char: char) -> BTreeSet<char> {
+        match self.case_insensitive {
+            true => case_variants(char),
+            false => [char].into(),
+        }
+    }
+}
+
+/// Returns `char` and the characters it can be converted to by changing the case repeatedly.
+/// A conversion that results in several characters (e.g. 'ß' to "SS") is ignored.
+fn case_variants(char: char) -> BTreeSet<char> {
+    fn single(mut chars: impl Iterator<Item = char>) -> Option<char> {
+        let ret = chars.next();
+        chars.next().is_none().then_some(ret).flatten()
+    }
+    let mut ret = BTreeSet::from([char]);
+    let mut stack = vec![char];
+    while let Some(char) = stack.pop() {
+        let variants = [single(char.to_lowercase()), single(char.to_uppercase())];
+        for variant in variants.into_iter().flatten() {
+            if ret.insert(variant) {
+                stack.push(variant);
+            }
+        }
+    }
+    ret
+}
+
+/// A zero-width assertion on the position in the text.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Anchor {
+    Start,
+    End,
+}
+
+/// A part of the automaton under construction, which is assembled from a node.
+struct Fragment {
+    start: NFAState,
+    accepts: HashSet<NFAState>,
+}
+
+/// A step of assembling the nodes in post-order, without recursion.
+enum Task<'a> {
+    /// Assembles the children of the node, then the node itself.
+    Visit(&'a Node),
+    /// Assembles the node from the fragments of its children.
+    Combine(&'a Node),
 }
 
 impl Node {
     fn assemble(&self, context: &mut Context) -> NondeterministicFiniteAutomaton {
-        match self {
+        // The transitions of all fragments are added to `nfa`, whose start and accepts are set last.
+        let mut nfa = NondeterministicFiniteAutomaton::new(NFAState(0), HashSet::new());
+        let mut fragments = Vec::<Fragment>::new();
+        let mut tasks = vec![Task::Visit(self)];
+        while let Some(task) = tasks.pop() {
+            match task {
+                Task::Visit(node @ Node::Star(child)) => {
+                    tasks.extend([Task::Combine(node), Task::Visit(child)]);
+                }
+                Task::Visit(
+                    node @ (Node::Union(child1, child2) | Node::Concat(child1, child2)),
+                ) => {
+                    tasks.extend([
+                        Task::Combine(node),
+                        Task::Visit(child2),
+                        Task::Visit(child1),
+                    ]);
+                }
+                Task::Visit(node) | Task::Combine(node) => {
+                    nfa = node.assemble_fragment(context, nfa, &mut fragments);
+                }
+            }
+        }
+        let fragment = fragments.pop().unwrap();
+        nfa.start = fragment.start;
+        nfa.accepts = fragment.accepts;
+        nfa
+    }
+
+    /// Adds the transitions of this node to `nfa` and pushes its fragment. The fragments of the
+    /// children must be on top of `fragments`.
+    fn assemble_fragment(
+        &self,
+        context: &mut Context,
+        mut nfa: NondeterministicFiniteAutomaton,
+        fragments: &mut Vec<Fragment>,
+    ) -> NondeterministicFiniteAutomaton {
+        let fragment = match self {
             Node::Character(char) => {
                 let start = context.new_state();
                 let accept = context.new_state();
-                NondeterministicFiniteAutomaton::new(start, [accept].into())
-                    .add_transition(start, *char, accept)
+                for char in context.variants(*char) {
+                    nfa = nfa.add_transition(start, char, accept);
+                }
+                Fragment {
+                    start,
+                    accepts: [accept].into(),
+                }
+            }
+            Node::AnyChar => {
+                let start = context.new_state();
+                let accept = context.new_state();
+                nfa = match context.dot_matches_newline {
+                    true => nfa.add_any_transition(start, accept),
+                    false => nfa.add_any_except_transition(start, ['\n'].into(), accept),
+                };
+                Fragment {
+                    start,
+                    accepts: [accept].into(),
+                }
+            }
+            Node::NegatedClass(chars) => {
+                let start = context.new_state();
+                let accept = context.new_state();
+                let excluded = chars.iter().flat_map(|c| context.variants(*c)).collect();
+                nfa = nfa.add_any_except_transition(start, excluded, accept);
+                Fragment {
+                    start,
+                    accepts: [accept].into(),
+                }
+            }
+            Node::Class(chars) => {
+                let start = context.new_state();
+                let accept = context.new_state();
+                for char in chars.iter().flat_map(|c| context.variants(*c)) {
+                    nfa = nfa.add_transition(start, char, accept);
+                }
+                Fragment {
+                    start,
+                    accepts: [accept].into(),
+                }
             }
             Node::Empty => {
                 let start = context.new_state();
                 let accept = context.new_state();
-                NondeterministicFiniteAutomaton::new(start, [accept].into())
-                    .add_empty_transition(start, accept)
+                nfa = nfa.add_empty_transition(start, accept);
+                Fragment {
+                    start,
+                    accepts: [accept].into(),
+                }
+            }
+            Node::StartAnchor | Node::EndAnchor => {
+                let start = context.new_state();
+                let accept = context.new_state();
+                let anchor = match self {
+                    Node::StartAnchor => Anchor::Start,
+                    _ => Anchor::End,
+                };
+                nfa.anchor_transition
+                    .entry(start)
+                    .or_default()
+                    .push((anchor, accept));
+                Fragment {
+                    start,
+                    accepts: [accept].into(),
+                }
             }
-            Node::Star(node) => {
-                let frag = node.assemble(context);
+            Node::Star(_) => {
+                let frag = fragments.pop().unwrap();
                 let start = context.new_state();
-                let accepts = frag.accepts.union(&[start].into()).cloned().collect();
-                let mut nfa = NondeterministicFiniteAutomaton::new(start, accepts)
-                    .merge_transition(&frag)
-                    .add_empty_transition(start, frag.start);
+                nfa = nfa.add_empty_transition(start, frag.start);
                 for accept in &frag.accepts {
                     nfa = nfa.add_empty_transition(*accept, frag.start);
                 }
-                nfa
+                let mut accepts = frag.accepts;
+                accepts.insert(start);
+                Fragment { start, accepts }
             }
-            Node::Union(node1, node2) => {
-                let frag1 = node1.assemble(context);
-                let frag2 = node2.assemble(context);
+            Node::Union(_, _) => {
+                let frag2 = fragments.pop().unwrap();
+                let frag1 = fragments.pop().unwrap();
                 let start = context.new_state();
-                let accepts = frag1.accepts.union(&frag2.accepts).cloned().collect();
-                NondeterministicFiniteAutomaton::new(start, accepts)
-                    .merge_transition(&frag1)
-                    .merge_transition(&frag2)
+                nfa = nfa
                     .add_empty_transition(start, frag1.start)
-                    .add_empty_transition(start, frag2.start)
-            }
-            Node::Concat(node1, node2) => {
-                let frag1 = node1.assemble(context);
-                let frag2 = node2.assemble(context);
-                let mut fragment =
-                    NondeterministicFiniteAutomaton::new(frag1.start, frag2.accepts.clone())
-                        .merge_transition(&frag1)
-                        .merge_transition(&frag2);
+                    .add_empty_transition(start, frag2.start);
+                // Extends the larger set, so that a long chain of unions takes linear time.
+                let (mut accepts, other) = match frag1.accepts.len() >= frag2.accepts.len() {
+                    true => (frag1.accepts, frag2.accepts),
+                    false => (frag2.accepts, frag1.accepts),
+                };
+                accepts.extend(other);
+                Fragment { start, accepts }
+            }
+            Node::Concat(_, _) => {
+                let frag2 = fragments.pop().unwrap();
+                let frag1 = fragments.pop().unwrap();
                 for accept1 in &frag1.accepts {
-                    fragment = fragment.add_empty_transition(*accept1, frag2.start);
+                    nfa = nfa.add_empty_transition(*accept1, frag2.start);
+                }
+                Fragment {
+                    start: frag1.start,
+                    accepts: frag2.accepts,
                 }
-                fragment
             }
-        }
+        };
+        fragments.push(fragment);
+        nfa
     }
 }
 
+#[derive(Clone)]
 pub struct NondeterministicFiniteAutomaton {
-    pub start: NFAState,
-    pub accepts: HashSet<NFAState>,
+    start: NFAState,
+    accepts: HashSet<NFAState>,
     transition: HashMap<NFAState, HashMap<Option<char>, HashSet<NFAState>>>,
+    /// any_transition[from][to] = The characters that can NOT be transitioned by.
+    any_transition: HashMap<NFAState, HashMap<NFAState, BTreeSet<char>>>,
+    /// anchor_transition[from] = The transitions by `^` and `$`. They are only present in an
+    /// automaton assembled from a node, until it is passed to `resolve_anchors`.
+    anchor_transition: HashMap<NFAState, Vec<(Anchor, NFAState)>>,
 }
 
 impl NondeterministicFiniteAutomaton {
-    pub fn new(start: NFAState, accepts: HashSet<NFAState>) -> Self {
+    pub(crate) fn new(start: NFAState, accepts: HashSet<NFAState>) -> Self {
         NondeterministicFiniteAutomaton {
             start,
             accepts,
             transition: HashMap::new(),
+            any_transition: HashMap::new(),
+            anchor_transition: HashMap::new(),
         }
     }
 
+    /// `.` matches any character except '\n'. `^` and `$` match at the start and the end of the
+    /// accepted string.
     pub fn from_node(node: Node) -> Self {
-        node.assemble(&mut Context::new())
+        Self::from_node_with_options(node, false, false).resolve_anchors(true, true)
+    }
+
+    /// Like `from_node`, but every character also matches its case variants.
+    /// Only single-character variants are considered, so e.g. 'ß' does not match "SS".
+    pub fn from_node_case_insensitive(node: Node) -> Self {
+        Self::from_node_with_options(node, true, false).resolve_anchors(true, true)
+    }
+
+    /// Assembles the automaton from `node`, keeping the transitions by `^` and `$` until
+    /// `resolve_anchors`.
+    pub(crate) fn from_node_with_options(
+        node: Node,
+        case_insensitive: bool,
+        dot_matches_newline: bool,
+    ) -> Self {
+        let ret = node.assemble(&mut Context::new(case_insensitive, dot_matches_newline));
+        drop_tree(node);
+        ret
+    }
+
+    /// Returns true if the automaton has transitions by `^` or `$` to be resolved.
+    pub(crate) fn has_anchors(&self) -> bool {
+        !self.anchor_transition.is_empty()
+    }
+
+    /// Returns the automaton without transitions by `^` and `$`, for a match that starts at the
+    /// start of the text if `at_text_start` and ends at the end of the text if `at_text_end`.
+    /// Otherwise `^` and `$` respectively never match. Even then, `^` only matches before the
+    /// first character of the match, and `$` after the last one.
+    pub(crate) fn resolve_anchors(&self, at_text_start: bool, at_text_end: bool) -> Self {
+        if !self.has_anchors() {
+            return self.clone();
+        }
+        // A state of the result is a state of this automaton, whether a character has been
+        // consumed, and whether `$` has been passed. The former only matters for `^` and the
+        // latter only for `$`, so they are left false when the anchor can not match anyway.
+        type Key = (NFAState, bool, bool);
+        fn id(key: Key, ids: &mut HashMap<Key, NFAState>, waiting: &mut Vec<Key>) -> NFAState {
+            let len = ids.len();
+            *ids.entry(key).or_insert_with(|| {
+                waiting.push(key);
+                NFAState(state_id(len))
+            })
+        }
+        let mut ids = HashMap::<Key, NFAState>::new();
+        let mut waiting = vec![];
+        let start = id((self.start, false, false), &mut ids, &mut waiting);
+        let mut nfa = NondeterministicFiniteAutomaton::new(start, HashSet::new());
+        while let Some(key @ (state, consumed, ended)) = waiting.pop() {
+            let from = ids[&key];
+            if self.accepts.contains(&state) {
+                nfa.accepts.insert(from);
+            }
+            for to in self.next_states(state, None) {
+                let to = id((to, consumed, ended), &mut ids, &mut waiting);
+                nfa = nfa.add_empty_transition(from, to);
+            }
+            for (anchor, to) in self.anchor_transition.get(&state).into_iter().flatten() {
+                let to = match anchor {
+                    Anchor::Start if at_text_start && !consumed => (*to, consumed, ended),
+                    Anchor::End if at_text_end => (*to, consumed, true),
+                    _ => continue,
+                };
+                let to = id(to, &mut ids, &mut waiting);
+                nfa = nfa.add_empty_transition(from, to);
+            }
+            if ended {
+                continue;
+            }
+            for char in self.next_chars(state).into_iter().flatten() {
+                for to in self.next_states(state, Some(char)) {
+                    let to = id((to, at_text_start, false), &mut ids, &mut waiting);
+                    nfa = nfa.add_transition(from, char, to);
+                }
+            }
+            for (to, excluded) in self.any_transition.get(&state).into_iter().flatten() {
+                let to = id((*to, at_text_start, false), &mut ids, &mut waiting);
+                nfa = nfa.add_any_except_transition(from, excluded.clone(), to);
+            }
+        }
+        nfa
+    }
+
+    /// Returns the automaton accepting the same strings as `dfa`, with the same state ids.
+    pub fn from_dfa(dfa: &DeterministicFiniteAutomaton) -> Self {
+        let state = |state: DFAState| NFAState(state.0);
+        let accepts = dfa.accepts().iter().map(|accept| state(*accept)).collect();
+        let mut nfa = NondeterministicFiniteAutomaton::new(state(dfa.start()), accepts);
+        let mut explicit_chars = HashMap::<DFAState, BTreeSet<char>>::new();
+        for (from, char, to) in dfa.transitions() {
+            nfa = nfa.add_transition(state(from), char, state(to));
+            explicit_chars.entry(from).or_default().insert(char);
+        }
+        // A default transition is taken by the characters without a transition of their own.
+        for (from, to) in dfa.default_transitions() {
+            let excluded = explicit_chars.remove(&from).unwrap_or_default();
+            nfa = nfa.add_any_except_transition(state(from), excluded, state(to));
+        }
+        nfa
+    }
+
+    /// Returns the automaton accepting a string this automaton accepts followed by a string
+    /// `other` accepts.
+    pub fn concat(&self, other: &Self) -> Self {
+        let other = other.shift(self.state_bound());
+        let mut nfa = NondeterministicFiniteAutomaton::new(self.start, other.accepts.clone())
+            .merge_transition(self)
+            .merge_transition(&other);
+        for accept in &self.accepts {
+            nfa = nfa.add_empty_transition(*accept, other.start);
+        }
+        nfa
+    }
+
+    /// Returns the automaton accepting the strings either automaton accepts.
+    pub fn union(&self, other: &Self) -> Self {
+        let other = other.shift(self.state_bound());
+        let start = NFAState(other.state_bound());
+        let accepts = self.accepts.union(&other.accepts).cloned().collect();
+        NondeterministicFiniteAutomaton::new(start, accepts)
+            .merge_transition(self)
+            .merge_transition(&other)
+            .add_empty_transition(start, self.start)
+            .add_empty_transition(start, other.start)
+    }
+
+    /// Returns the automaton accepting the strings any of `nfas` accepts, and the accept states
+    /// each of `nfas` ends up with, as its states are renumbered.
+    pub fn union_all(nfas: &[Self]) -> (Self, Vec<HashSet<NFAState>>) {
+        let mut offset = 0;
+        let nfas = nfas
+            .iter()
+            .map(|nfa| {
+                let nfa = nfa.shift(offset);
+                offset = nfa.state_bound();
+                nfa
+            })
+            .collect::<Vec<_>>();
+        let start = NFAState(offset);
+        let accepts = nfas.iter().flat_map(|nfa| nfa.accepts.clone()).collect();
+        let mut ret = NondeterministicFiniteAutomaton::new(start, accepts);
+        for nfa in &nfas {
+            ret = ret
+                .merge_transition(nfa)
+                .add_empty_transition(start, nfa.start);
+        }
+        (ret, nfas.into_iter().map(|nfa| nfa.accepts).collect())
+    }
+
+    /// Returns the automaton accepting the concatenations of zero or more strings this automaton
+    /// accepts.
+    pub fn star(&self) -> Self {
+        let start = NFAState(self.state_bound());
+        let accepts = self.accepts.union(&[start].into()).cloned().collect();
+        let mut nfa = NondeterministicFiniteAutomaton::new(start, accepts)
+            .merge_transition(self)
+            .add_empty_transition(start, self.start);
+        for accept in &self.accepts {
+            nfa = nfa.add_empty_transition(*accept, self.start);
+        }
+        nfa
+    }
+
+    /// Returns the automaton accepting the reversed strings this automaton accepts.
+    pub fn reverse(&self) -> Self {
+        let start = NFAState(self.state_bound());
+        let mut nfa = NondeterministicFiniteAutomaton::new(start, [self.start].into());
+        for accept in &self.accepts {
+            nfa = nfa.add_empty_transition(start, *accept);
+        }
+        for (from, table) in &self.transition {
+            for (char, to_states) in table {
+                for to in to_states {
+                    nfa._insert_transition(*to, *from, *char);
+                }
+            }
+        }
+        for (from, table) in &self.any_transition {
+            for (to, excluded) in table {
+                nfa = nfa.add_any_except_transition(*to, excluded.clone(), *from);
+            }
+        }
+        nfa
+    }
+
+    /// Returns the same automaton without the states whose only transition is an empty one, e.g.
+    /// the one `Concat` adds between its children. The transitions into such a state lead to the
+    /// state it skips to instead. Accept states and states with anchors are kept.
+    pub fn simplify(&self) -> Self {
+        let skip_to = |state: NFAState| {
+            let table = self.transition.get(&state)?;
+            let to_states = table.get(&None)?;
+            let removable = table.len() == 1
+                && to_states.len() == 1
+                && !self.accepts.contains(&state)
+                && !self.any_transition.contains_key(&state)
+                && !self.anchor_transition.contains_key(&state);
+            removable.then(|| *to_states.iter().next().unwrap())
+        };
+        // forward[state] = The state the transitions into `state` lead to instead. On a cycle of
+        // such states, the first one found is kept and the others lead to it.
+        let mut forward = HashMap::<NFAState, NFAState>::new();
+        let mut resolve = |state: NFAState| {
+            let mut chain = vec![];
+            let mut current = state;
+            let target = loop {
+                if let Some(target) = forward.get(&current) {
+                    break *target;
+                }
+                match skip_to(current) {
+                    Some(next) if !chain.contains(&current) => {
+                        chain.push(current);
+                        current = next;
+                    }
+                    _ => break current,
+                }
+            };
+            for state in chain {
+                forward.insert(state, target);
+            }
+            target
+        };
+
+        let mut nfa =
+            NondeterministicFiniteAutomaton::new(resolve(self.start), self.accepts.clone());
+        for (from, table) in &self.transition {
+            if resolve(*from) != *from {
+                continue;
+            }
+            for (char, to_states) in table {
+                for to in to_states {
+                    nfa._insert_transition(*from, resolve(*to), *char);
+                }
+            }
+        }
+        for (from, table) in &self.any_transition {
+            for (to, excluded) in table {
+                nfa = nfa.add_any_except_transition(*from, excluded.clone(), resolve(*to));
+            }
+        }
+        for (from, transitions) in &self.anchor_transition {
+            for (anchor, to) in transitions {
+                let to = resolve(*to);
+                nfa.anchor_transition
+                    .entry(*from)
+                    .or_default()
+                    .push((*anchor, to));
+            }
+        }
+        nfa
+    }
+
+    /// Returns one more than the largest state id.
+    pub(crate) fn state_bound(&self) -> u32 {
+        let transition = self.transition.iter().flat_map(|(from, table)| {
+            let to_states = table.values().flatten();
+            [from].into_iter().chain(to_states)
+        });
+        let any_transition = self
+            .any_transition
+            .iter()
+            .flat_map(|(from, table)| [from].into_iter().chain(table.keys()));
+        [&self.start]
+            .into_iter()
+            .chain(&self.accepts)
+            .chain(transition)
+            .chain(any_transition)
+            .map(|state| state.0 + 1)
+            .max()
+            .unwrap()
+    }
+
+    /// Returns the same automaton with every state id increased by `offset`.
+    fn shift(&self, offset: u32) -> Self {
+        let shift = |state: &NFAState| NFAState(state.0 + offset);
+        let accepts = self.accepts.iter().map(shift).collect();
+        let mut nfa = NondeterministicFiniteAutomaton::new(shift(&self.start), accepts);
+        for (from, table) in &self.transition {
+            for (char, to_states) in table {
+                for to in to_states {
+                    nfa._insert_transition(shift(from), shift(to), *char);
+                }
+            }
+        }
+        for (from, table) in &self.any_transition {
+            for (to, excluded) in table {
+                nfa = nfa.add_any_except_transition(shift(from), excluded.clone(), shift(to));
+            }
+        }
+        nfa
+    }
+
+    pub fn start(&self) -> NFAState {
+        self.start
+    }
+
+    pub fn accepts(&self) -> &HashSet<NFAState> {
+        &self.accepts
+    }
+
+    /// Returns the states reachable from `state` by empty transitions, including `state`.
+    pub fn epsilon_closure(&self, state: NFAState) -> HashSet<NFAState> {
+        self.epsilon_closure_all([state]).into_iter().collect()
+    }
+
+    /// Like `epsilon_closure`, but from all of `states` at once.
+    pub(crate) fn epsilon_closure_all(
+        &self,
+        states: impl IntoIterator<Item = NFAState>,
+    ) -> Vec<NFAState> {
+        let mut ret = HashSet::<NFAState>::new();
+        let mut stack = states.into_iter().collect::<Vec<_>>();
+        while let Some(state) = stack.pop() {
+            if ret.insert(state) {
+                stack.extend(self.next_states(state, None));
+            }
+        }
+        ret.into_iter().collect()
     }
 
     pub fn next_chars(&self, state: NFAState) -> HashSet<Option<char>> {
         self.transition
             .get(&state)
             .map(|table| table.keys().cloned().collect())
-            .unwrap_or(HashSet::new())
+            .unwrap_or_default()
     }
 
     pub fn next_states(&self, state: NFAState, char: Option<char>) -> HashSet<NFAState> {
@@ -105,30 +589,76 @@ impl NondeterministicFiniteAutomaton {
             .get(&state)
             .and_then(|table| table.get(&char))
             .cloned()
-            .unwrap_or(HashSet::new())
+            .unwrap_or_default()
+    }
+
+    /// Returns the characters excluded from any of the `any` transitions of `state`.
+    pub fn excluded_chars(&self, state: NFAState) -> HashSet<char> {
+        self.any_transition
+            .get(&state)
+            .map(|table| table.values().flatten().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns the states that can be transitioned by `char` via `any` transitions.
+    /// `None` stands for a character that is not in `excluded_chars(state)`.
+    pub fn next_any_states(&self, state: NFAState, char: Option<char>) -> HashSet<NFAState> {
+        self.any_transition
+            .get(&state)
+            .map(|table| {
+                table
+                    .iter()
+                    .filter(|(_, excluded)| char.is_none_or(|c| !excluded.contains(&c)))
+                    .map(|(to, _)| *to)
+                    .collect()
+            })
+            .unwrap_or_default()
     }
 
-    pub fn add_transition(mut self, from: NFAState, char: char, to: NFAState) -> Self {
+    pub(crate) fn add_transition(mut self, from: NFAState, char: char, to: NFAState) -> Self {
         self._insert_transition(from, to, Some(char));
         self
     }
 
-    pub fn add_empty_transition(mut self, from: NFAState, to: NFAState) -> Self {
+    pub(crate) fn add_empty_transition(mut self, from: NFAState, to: NFAState) -> Self {
         self._insert_transition(from, to, None);
         self
     }
 
+    pub(crate) fn add_any_transition(self, from: NFAState, to: NFAState) -> Self {
+        self.add_any_except_transition(from, BTreeSet::new(), to)
+    }
+
+    pub(crate) fn add_any_except_transition(
+        mut self,
+        from: NFAState,
+        excluded: BTreeSet<char>,
+        to: NFAState,
+    ) -> Self {
+        self.any_transition
+            .entry(from)
+            .or_default()
+            .insert(to, excluded);
+        self
+    }
+
     fn merge_transition(mut self, other: &Self) -> Self {
         for (from_state, trans) in &other.transition {
             for (char, to_states) in trans {
                 self.transition
                     .entry(*from_state)
-                    .or_insert(HashMap::new())
+                    .or_default()
                     .entry(*char)
-                    .or_insert(HashSet::new())
+                    .or_default()
                     .extend(to_states);
             }
         }
+        for (from_state, trans) in &other.any_transition {
+            self.any_transition
+                .entry(*from_state)
+                .or_default()
+                .extend(trans.clone());
+        }
         self
     }
 
@@ -136,25 +666,94 @@ impl NondeterministicFiniteAutomaton {
         let to_states = self
             .transition
             .entry(from)
-            .or_insert(HashMap::new())
+            .or_default()
             .entry(char)
-            .or_insert(HashSet::new());
+            .or_default();
         to_states.insert(to);
     }
 }
 
+/// Simulates the automaton by tracking the set of all current states.
+impl Automaton for NondeterministicFiniteAutomaton {
+    type State = Vec<NFAState>;
+
+    fn start_state(&self) -> Vec<NFAState> {
+        self.epsilon_closure_all([self.start])
+    }
+
+    fn step(&self, states: &Vec<NFAState>, char: char) -> Option<Vec<NFAState>> {
+        let next_states = states.iter().flat_map(|state| {
+            self.next_states(*state, Some(char))
+                .into_iter()
+                .chain(self.next_any_states(*state, Some(char)))
+        });
+        let ret = self.epsilon_closure_all(next_states);
+        (!ret.is_empty()).then_some(ret)
+    }
+
+    fn is_accept(&self, states: &Vec<NFAState>) -> bool {
+        states.iter().any(|state| self.accepts.contains(state))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn context() {
-        let mut context = Context::new();
+        let mut context = Context::new(false, false);
         assert_eq!(context.new_state(), NFAState(0));
         assert_eq!(context.new_state(), NFAState(1));
         assert_eq!(context.new_state(), NFAState(2));
     }
 
+    #[test]
+    #[should_panic(expected = "too many states")]
+    fn context_overflow() {
+        let mut context = Context::new(false, false);
+        context.state_count = u32::MAX - 1;
+        assert_eq!(context.new_state(), NFAState(u32::MAX - 1));
+        assert_eq!(context.state_count, u32::MAX);
+        context.new_state();
+    }
+
+    #[test]
+    fn case_variants() {
+        assert_eq!(super::case_variants('a'), ['A', 'a'].into());
+        assert_eq!(super::case_variants('Σ'), ['Σ', 'σ'].into());
+        assert_eq!(super::case_variants('k'), ['K', 'k', 'K'].into());
+        assert_eq!(super::case_variants('ẞ'), ['ß', 'ẞ'].into());
+        assert_eq!(super::case_variants('ß'), ['ß'].into());
+        assert_eq!(super::case_variants('1'), ['1'].into());
+    }
+
+    #[test]
+    fn from_dfa() {
+        let node = Node::Concat(
+            Box::new(Node::NegatedClass(['a'].into())),
+            Box::new(Node::Character('b')),
+        );
+        let dfa = DeterministicFiniteAutomaton::from_nfa(
+            NondeterministicFiniteAutomaton::from_node(node),
+        );
+        let nfa = NondeterministicFiniteAutomaton::from_dfa(&dfa);
+        assert_eq!(nfa.start, NFAState(dfa.start().0));
+        for text in ["xb", "山b", "ab", "bb", "x", "xbb"] {
+            let mut state = Some(nfa.start_state());
+            for char in text.chars() {
+                state = state.and_then(|state| nfa.step(&state, char));
+            }
+            let expected =
+                text.chars().count() == 2 && text.ends_with('b') && !text.starts_with('a');
+            assert_eq!(
+                state.is_some_and(|state| nfa.is_accept(&state)),
+                expected,
+                "{text}"
+            );
+        }
+    }
+
     #[test]
     fn from_character_node() {
         let nfa = NondeterministicFiniteAutomaton::from_node(Node::Character('a'));
@@ -183,6 +782,118 @@ mod tests {
         );
     }
 
+    #[test]
+    fn from_any_char_node() {
+        let nfa = NondeterministicFiniteAutomaton::from_node(Node::AnyChar);
+
+        // -> 0 --any but \n--> 1
+        // accept: 1
+        assert_eq!(nfa.start, NFAState(0));
+        assert_eq!(nfa.accepts, [NFAState(1)].into());
+        assert!(nfa.transition.is_empty());
+        assert_eq!(
+            nfa.any_transition,
+            [(NFAState(0), [(NFAState(1), ['\n'].into())].into())].into()
+        );
+
+        let nfa =
+            NondeterministicFiniteAutomaton::from_node_with_options(Node::AnyChar, false, true);
+
+        // -> 0 --any--> 1
+        // accept: 1
+        assert_eq!(
+            nfa.any_transition,
+            [(NFAState(0), [(NFAState(1), [].into())].into())].into()
+        );
+    }
+
+    #[test]
+    fn from_negated_class_node() {
+        let nfa = NondeterministicFiniteAutomaton::from_node(Node::NegatedClass(['a'].into()));
+
+        // -> 0 --any but a--> 1
+        // accept: 1
+        assert_eq!(nfa.start, NFAState(0));
+        assert_eq!(nfa.accepts, [NFAState(1)].into());
+        assert!(nfa.transition.is_empty());
+        assert_eq!(
+            nfa.any_transition,
+            [(NFAState(0), [(NFAState(1), ['a'].into())].into())].into()
+        );
+        assert_eq!(nfa.excluded_chars(NFAState(0)), ['a'].into());
+        assert_eq!(nfa.next_any_states(NFAState(0), Some('a')), [].into());
+        assert_eq!(
+            nfa.next_any_states(NFAState(0), Some('b')),
+            [NFAState(1)].into()
+        );
+        assert_eq!(nfa.next_any_states(NFAState(0), None), [NFAState(1)].into());
+    }
+
+    #[test]
+    fn from_class_node() {
+        let nfa = NondeterministicFiniteAutomaton::from_node(Node::Class(['a', 'b'].into()));
+
+        // -> 0 --a,b--> 1
+        // accept: 1
+        assert_eq!(nfa.start, NFAState(0));
+        assert_eq!(nfa.accepts, [NFAState(1)].into());
+        assert_eq!(
+            nfa.transition,
+            [(
+                NFAState(0),
+                [
+                    (Some('a'), [NFAState(1)].into()),
+                    (Some('b'), [NFAState(1)].into())
+                ]
+                .into()
+            )]
+            .into()
+        );
+    }
+
+    #[test]
+    fn simulate() {
+        let nfa = NondeterministicFiniteAutomaton::from_node(Node::Star(Box::new(Node::Class(
+            ['a', 'b'].into(),
+        ))));
+        let mut states = nfa.start_state();
+        assert!(nfa.is_accept(&states));
+        for char in "abba".chars() {
+            states = nfa.step(&states, char).unwrap();
+            assert!(nfa.is_accept(&states));
+        }
+        assert_eq!(nfa.step(&states, 'c'), None);
+    }
+
+    #[test]
+    fn resolve_anchors() {
+        // ^a|b$
+        let node = Node::Union(
+            Box::new(Node::Concat(
+                Box::new(Node::StartAnchor),
+                Box::new(Node::Character('a')),
+            )),
+            Box::new(Node::Concat(
+                Box::new(Node::Character('b')),
+                Box::new(Node::EndAnchor),
+            )),
+        );
+        let nfa = NondeterministicFiniteAutomaton::from_node_with_options(node, false, false);
+        assert!(nfa.has_anchors());
+        let accepts = |nfa: &NondeterministicFiniteAutomaton, char| {
+            nfa.step(&nfa.start_state(), char)
+                .is_some_and(|states| nfa.is_accept(&states))
+        };
+        for (at_text_start, at_text_end) in
+            [(true, true), (true, false), (false, true), (false, false)]
+        {
+            let resolved = nfa.resolve_anchors(at_text_start, at_text_end);
+            assert!(!resolved.has_anchors());
+            assert_eq!(accepts(&resolved, 'a'), at_text_start);
+            assert_eq!(accepts(&resolved, 'b'), at_text_end);
+        }
+    }
+
     #[test]
     fn from_star_node() {
         let nfa =
@@ -204,6 +915,71 @@ mod tests {
         );
     }
 
+    #[test]
+    fn simplify() {
+        let node = (0..50).fold(Node::Character('a'), |node, i| {
+            let char = char::from(b'a' + i % 26);
+            Node::Concat(Box::new(node), Box::new(Node::Character(char)))
+        });
+        let nfa = NondeterministicFiniteAutomaton::from_node(node);
+        let simplified = nfa.simplify();
+        let state_count = |nfa: &NondeterministicFiniteAutomaton| {
+            let mut states: HashSet<_> = [nfa.start].into();
+            for (from, table) in &nfa.transition {
+                states.insert(*from);
+                states.extend(table.values().flatten());
+            }
+            states.len()
+        };
+        assert_eq!(state_count(&nfa), 102);
+        assert_eq!(state_count(&simplified), 52);
+
+        let dfa = DeterministicFiniteAutomaton::from_nfa(nfa);
+        let simplified = DeterministicFiniteAutomaton::from_nfa(simplified);
+        assert_eq!(simplified.state_count(), dfa.state_count());
+        assert!(simplified.equivalent(&dfa));
+
+        // -> 0 --ε--> 1 --ε--> 2 --ε--> 1, 0 --a--> 3
+        // accept: 3
+        let nfa = NondeterministicFiniteAutomaton::new(NFAState(0), [NFAState(3)].into())
+            .add_empty_transition(NFAState(0), NFAState(1))
+            .add_empty_transition(NFAState(1), NFAState(2))
+            .add_empty_transition(NFAState(2), NFAState(1))
+            .add_transition(NFAState(0), 'a', NFAState(3))
+            .simplify();
+        let states = nfa.start_state();
+        assert!(!nfa.is_accept(&states));
+        assert!(nfa.is_accept(&nfa.step(&states, 'a').unwrap()));
+    }
+
+    #[test]
+    fn epsilon_closure() {
+        let nfa =
+            NondeterministicFiniteAutomaton::from_node(Node::Star(Box::new(Node::Character('a'))));
+
+        // See `from_star_node` for the states.
+        assert_eq!(
+            nfa.epsilon_closure(NFAState(2)),
+            [NFAState(2), NFAState(0)].into()
+        );
+        assert_eq!(
+            nfa.epsilon_closure(NFAState(1)),
+            [NFAState(1), NFAState(0)].into()
+        );
+        assert_eq!(nfa.epsilon_closure(NFAState(0)), [NFAState(0)].into());
+
+        let closure = |states: &[NFAState]| {
+            nfa.epsilon_closure_all(states.iter().cloned())
+                .into_iter()
+                .collect::<HashSet<_>>()
+        };
+        assert_eq!(
+            closure(&[NFAState(0), NFAState(1)]),
+            [NFAState(0), NFAState(1)].into()
+        );
+        assert_eq!(closure(&[]), HashSet::new());
+    }
+
     #[test]
     fn from_union_node() {
         let nfa = NondeterministicFiniteAutomaton::from_node(Node::Union(
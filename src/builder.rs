@@ -0,0 +1,220 @@
+use crate::automaton::*;
+use crate::error::RegexError;
+use crate::prelude::*;
+#[cfg(feature = "unicode-normalization")]
+use crate::{lexer::Lexer, parser::Parser};
+use crate::{parse, Anchored, Engine, Node, Regex};
+
+/// How a compiled regex matches a text.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// Builds the complete DFA when compiling.
+    #[default]
+    Dfa,
+    /// Simulates the NFA directly, to avoid the state explosion of the subset construction.
+    Nfa,
+    /// Builds the DFA while matching, only as far as the inputs require.
+    LazyDfa,
+}
+
+/// The Unicode normalization form the pattern and the matched texts are converted to.
+#[cfg(feature = "unicode-normalization")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Normalization {
+    /// Keeps the characters as they are.
+    #[default]
+    None,
+    /// Canonical composition, e.g. 'e' followed by U+0301 becomes 'é'.
+    Nfc,
+}
+
+/// Compiles a regex with options other than the defaults of [`Regex::new`].
+#[derive(Clone, Debug)]
+pub struct RegexBuilder {
+    pattern: String,
+    case_insensitive: bool,
+    dot_matches_newline: bool,
+    minimize: bool,
+    max_states: Option<usize>,
+    backend: Backend,
+    #[cfg(feature = "unicode-normalization")]
+    normalization: Normalization,
+    whole_word: bool,
+}
+
+impl RegexBuilder {
+    pub fn new(pattern: &str) -> RegexBuilder {
+        RegexBuilder {
+            pattern: pattern.to_string(),
+            case_insensitive: false,
+            dot_matches_newline: false,
+            minimize: false,
+            max_states: None,
+            backend: Backend::default(),
+            #[cfg(feature = "unicode-normalization")]
+            normalization: Normalization::default(),
+            whole_word: false,
+        }
+    }
+
+    /// Makes every character also match its case variants, e.g. `Hello` matches "HELLO".
+    /// Conversions into several characters are ignored, so 'ß' matches neither "SS" nor 'ẞ',
+    /// while 'ẞ' matches 'ß'.
+    pub fn case_insensitive(&mut self, yes: bool) -> &mut RegexBuilder {
+        self.case_insensitive = yes;
+        self
+    }
+
+    /// Makes `.` match '\n', too. By default it matches any character except '\n'.
+    pub fn dot_matches_newline(&mut self, yes: bool) -> &mut RegexBuilder {
+        self.dot_matches_newline = yes;
+        self
+    }
+
+    /// Minimizes the DFA. Only takes effect with [`Backend::Dfa`].
+    pub fn minimize(&mut self, yes: bool) -> &mut RegexBuilder {
+        self.minimize = yes;
+        self
+    }
+
+    /// Makes a match found in a larger text, e.g. by [`Regex::find`], [`Regex::contains`] or
+    /// [`Regex::match_ends`], start and end at the edges of the text or next to a non-word
+    /// character, like `\bcat\b`. A non-word character is one that `\w` does not match.
+    /// [`Regex::matches`] is unaffected.
+    pub fn whole_word(&mut self, yes: bool) -> &mut RegexBuilder {
+        self.whole_word = yes;
+        self
+    }
+
+    /// Makes [`RegexBuilder::build`] fail with [`RegexError::TooLarge`] as soon as the DFA turns
+    /// out to need more than `max_states` states, instead of building it whatever its size.
+    /// Only takes effect with [`Backend::Dfa`].
+    pub fn max_states(&mut self, max_states: usize) -> &mut RegexBuilder {
+        self.max_states = Some(max_states);
+        self
+    }
+
+    pub fn backend(&mut self, backend: Backend) -> &mut RegexBuilder {
+        self.backend = backend;
+        self
+    }
+
+    /// Normalizes the pattern before parsing it, and every text given to the regex before
+    /// matching it, so that both spellings of a character like 'é' match each other. Error spans
+    /// are then byte offsets into the normalized pattern.
+    ///
+    /// The offsets the search methods such as [`Regex::find`] return are still in the given text.
+    /// As a character is normalized together with the combining marks after it, a match starting
+    /// or ending among them is widened to all of them, and [`Regex::is_match_at`] finds no match
+    /// starting there. A [`crate::Matcher`] normalizes the text given to `feed_str`, but not the
+    /// characters given to `feed` one at a time.
+    #[cfg(feature = "unicode-normalization")]
+    pub fn normalize(&mut self, normalization: Normalization) -> &mut RegexBuilder {
+        self.normalization = normalization;
+        self
+    }
+
+    pub fn build(&self) -> Result<Regex, RegexError> {
+        #[cfg(feature = "unicode-normalization")]
+        if self.normalization == Normalization::Nfc {
+            use unicode_normalization::UnicodeNormalization;
+            let node = Parser::new(Lexer::from_chars(self.pattern.nfc()))?.parse()?;
+            return self.build_node(node);
+        }
+        self.build_node(parse(&self.pattern)?)
+    }
+
+    /// Compiles `node` instead of the pattern, with the other options.
+    pub(crate) fn build_node(&self, node: Node) -> Result<Regex, RegexError> {
+        let nfa = NondeterministicFiniteAutomaton::from_node_with_options(
+            node,
+            self.case_insensitive,
+            self.dot_matches_newline,
+        );
+        let anchored = match nfa.has_anchors() {
+            true => Some(Box::new(Anchored {
+                start: self.engine(nfa.resolve_anchors(true, false))?,
+                end: self.engine(nfa.resolve_anchors(false, true))?,
+                inner: self.engine(nfa.resolve_anchors(false, false))?,
+            })),
+            false => None,
+        };
+        Ok(Regex {
+            engine: self.engine(nfa.resolve_anchors(true, true))?,
+            anchored,
+            tags: None,
+            #[cfg(feature = "unicode-normalization")]
+            normalization: self.normalization,
+            whole_word: self.whole_word,
+        })
+    }
+
+    /// Compiles the union of `nodes`, remembering which of them each DFA state accepts for.
+    /// The DFA is always built and never minimized, as minimizing would merge states of different
+    /// alternatives.
+    pub(crate) fn build_tagged(&self, nodes: Vec<Node>) -> Result<Regex, RegexError> {
+        let nfas = nodes
+            .into_iter()
+            .map(|node| {
+                NondeterministicFiniteAutomaton::from_node_with_options(
+                    node,
+                    self.case_insensitive,
+                    self.dot_matches_newline,
+                )
+            })
+            .collect::<Vec<_>>();
+        let union = |at_text_start, at_text_end| {
+            let nfas = nfas
+                .iter()
+                .map(|nfa| nfa.resolve_anchors(at_text_start, at_text_end))
+                .collect::<Vec<_>>();
+            NondeterministicFiniteAutomaton::union_all(&nfas)
+        };
+        let anchored = match nfas.iter().any(|nfa| nfa.has_anchors()) {
+            true => Some(Box::new(Anchored {
+                start: self.engine(union(true, false).0)?,
+                end: self.engine(union(false, true).0)?,
+                inner: self.engine(union(false, false).0)?,
+            })),
+            false => None,
+        };
+        let (nfa, accepts) = union(true, true);
+        let tags = accepts
+            .into_iter()
+            .enumerate()
+            .flat_map(|(i, accepts)| accepts.into_iter().map(move |accept| (accept, i)))
+            .collect();
+        let (dfa, tags) = DeterministicFiniteAutomaton::from_nfa_with_tags(nfa, &tags);
+        let tags = tags
+            .into_iter()
+            .filter_map(|(state, tags)| Some((state, *tags.first()?)))
+            .collect();
+        Ok(Regex {
+            engine: Engine::Dfa(dfa),
+            anchored,
+            tags: Some(tags),
+            #[cfg(feature = "unicode-normalization")]
+            normalization: self.normalization,
+            whole_word: self.whole_word,
+        })
+    }
+
+    fn engine(&self, nfa: NondeterministicFiniteAutomaton) -> Result<Engine, RegexError> {
+        let nfa = nfa.simplify();
+        Ok(match self.backend {
+            Backend::Dfa => {
+                let dfa = match self.max_states {
+                    Some(limit) => DeterministicFiniteAutomaton::from_nfa_with_limit(nfa, limit)
+                        .ok_or(RegexError::TooLarge { limit })?,
+                    None => DeterministicFiniteAutomaton::from_nfa(nfa),
+                };
+                match self.minimize {
+                    true => Engine::Dfa(dfa.minimize()),
+                    false => Engine::Dfa(dfa),
+                }
+            }
+            Backend::Nfa => Engine::Nfa(nfa),
+            Backend::LazyDfa => Engine::Lazy(LazyDeterministicFiniteAutomaton::from_nfa(nfa)),
+        })
+    }
+}
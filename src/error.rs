@@ -0,0 +1,258 @@
+use crate::prelude::*;
+use core::fmt::Display;
+
+/// An error in a pattern. `span` is the byte offset where the error is found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RegexError {
+    UnexpectedToken {
+        expected: Vec<String>,
+        found: String,
+        span: usize,
+    },
+    UnbalancedParen {
+        span: usize,
+    },
+    TrailingEscape {
+        span: usize,
+    },
+    EmptyClass {
+        span: usize,
+    },
+    InvalidEscape {
+        escape: String,
+        span: usize,
+    },
+    InvalidRange {
+        first: char,
+        last: char,
+        span: usize,
+    },
+    InvalidRepetition {
+        min: u32,
+        max: u32,
+        span: usize,
+    },
+    RepetitionTooLarge {
+        span: usize,
+    },
+    NegatedClassEscapeInClass {
+        char: char,
+        span: usize,
+    },
+    UnsupportedGroup {
+        group: String,
+        span: usize,
+    },
+    /// A construct from other regex flavors that this crate can not express, such as
+    /// backreferences or lookaround.
+    Unsupported {
+        reason: &'static str,
+        span: usize,
+    },
+    /// The DFA needs more states than [`RegexBuilder::max_states`](crate::RegexBuilder::max_states)
+    /// allows. Its span is 0, as no part of the pattern is to blame.
+    TooLarge {
+        limit: usize,
+    },
+}
+
+impl RegexError {
+    pub fn span(&self) -> usize {
+        match self {
+            RegexError::UnexpectedToken { span, .. }
+            | RegexError::UnbalancedParen { span }
+            | RegexError::TrailingEscape { span }
+            | RegexError::EmptyClass { span }
+            | RegexError::InvalidEscape { span, .. }
+            | RegexError::InvalidRange { span, .. }
+            | RegexError::InvalidRepetition { span, .. }
+            | RegexError::RepetitionTooLarge { span }
+            | RegexError::NegatedClassEscapeInClass { span, .. }
+            | RegexError::UnsupportedGroup { span, .. }
+            | RegexError::Unsupported { span, .. } => *span,
+            RegexError::TooLarge { .. } => 0,
+        }
+    }
+}
+
+impl Display for RegexError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            RegexError::UnexpectedToken {
+                expected, found, ..
+            } => {
+                let expected = expected
+                    .iter()
+                    .map(|token| format!("'{}'", token))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "Expected one of [{}], found '{}'", expected, found)?;
+            }
+            RegexError::UnbalancedParen { .. } => write!(f, "Unbalanced parenthesis")?,
+            RegexError::TrailingEscape { .. } => write!(f, "Unexpected end of pattern after '\\'")?,
+            RegexError::EmptyClass { .. } => write!(f, "Empty character class")?,
+            RegexError::InvalidEscape { escape, .. } => write!(f, "Invalid escape '{}'", escape)?,
+            RegexError::InvalidRange { first, last, .. } => {
+                write!(f, "Invalid range '{}-{}'", first, last)?
+            }
+            RegexError::InvalidRepetition { min, max, .. } => write!(
+                f,
+                "Invalid repetition '{{{},{}}}', min is greater than max",
+                min, max
+            )?,
+            RegexError::RepetitionTooLarge { .. } => write!(f, "Repetition count is too large")?,
+            RegexError::NegatedClassEscapeInClass { char, .. } => write!(
+                f,
+                "Negated class escape '\\{}' can not be used in a character class",
+                char
+            )?,
+            RegexError::UnsupportedGroup { group, .. } => {
+                write!(f, "Unsupported group '{}', only '(?:' is supported", group)?
+            }
+            RegexError::Unsupported { reason, .. } => write!(f, "Unsupported pattern, {}", reason)?,
+            RegexError::TooLarge { limit } => {
+                return write!(f, "The DFA needs more than {} states", limit);
+            }
+        }
+        write!(f, " at byte {}", self.span())
+    }
+}
+
+#[cfg(not(feature = "no_std"))]
+impl std::error::Error for RegexError {}
+
+/// An error in the bytes of a serialized automaton. `offset` is the byte offset where the error
+/// is found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DeserializeError {
+    InvalidHeader {
+        offset: usize,
+    },
+    UnexpectedEnd {
+        offset: usize,
+    },
+    TrailingBytes {
+        offset: usize,
+    },
+    InvalidChar {
+        value: u32,
+        offset: usize,
+    },
+    InvalidState {
+        id: u32,
+        offset: usize,
+    },
+    /// More states than the rest of the bytes can mention, as every state but the start is in
+    /// some transition or accept state of a serialized automaton.
+    TooManyStates {
+        count: u32,
+        offset: usize,
+    },
+}
+
+impl DeserializeError {
+    pub fn offset(&self) -> usize {
+        match self {
+            DeserializeError::InvalidHeader { offset }
+            | DeserializeError::UnexpectedEnd { offset }
+            | DeserializeError::TrailingBytes { offset }
+            | DeserializeError::InvalidChar { offset, .. }
+            | DeserializeError::InvalidState { offset, .. }
+            | DeserializeError::TooManyStates { offset, .. } => *offset,
+        }
+    }
+}
+
+impl Display for DeserializeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            DeserializeError::InvalidHeader { .. } => write!(f, "Invalid header")?,
+            DeserializeError::UnexpectedEnd { .. } => write!(f, "Unexpected end of bytes")?,
+            DeserializeError::TrailingBytes { .. } => write!(f, "Unexpected trailing bytes")?,
+            DeserializeError::InvalidChar { value, .. } => {
+                write!(f, "Invalid character U+{:X}", value)?
+            }
+            DeserializeError::InvalidState { id, .. } => write!(f, "Invalid state {}", id)?,
+            DeserializeError::TooManyStates { count, .. } => {
+                write!(f, "Too many states {} for the rest of the bytes", count)?
+            }
+        }
+        write!(f, " at byte {}", self.offset())
+    }
+}
+
+#[cfg(not(feature = "no_std"))]
+impl std::error::Error for DeserializeError {}
+
+/// An error in an automaton written as a table. `line` is the 1-based line where the error is
+/// found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TableError {
+    MissingHeader {
+        expected: &'static str,
+        line: usize,
+    },
+    InvalidRow {
+        line: usize,
+    },
+    InvalidState {
+        id: u32,
+        line: usize,
+    },
+    DuplicateTransition {
+        line: usize,
+    },
+    /// The state count is larger than the number of states in the table, counting the start and
+    /// accept states.
+    UnusedStates {
+        count: u32,
+        line: usize,
+    },
+}
+
+impl TableError {
+    pub fn line(&self) -> usize {
+        match self {
+            TableError::MissingHeader { line, .. }
+            | TableError::InvalidRow { line }
+            | TableError::InvalidState { line, .. }
+            | TableError::DuplicateTransition { line }
+            | TableError::UnusedStates { line, .. } => *line,
+        }
+    }
+}
+
+impl Display for TableError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            TableError::MissingHeader { expected, .. } => write!(f, "Expected '{}'", expected)?,
+            TableError::InvalidRow { .. } => write!(f, "Invalid row")?,
+            TableError::InvalidState { id, .. } => write!(f, "Invalid state {}", id)?,
+            TableError::DuplicateTransition { .. } => write!(f, "Duplicate transition")?,
+            TableError::UnusedStates { count, .. } => write!(
+                f,
+                "State count {} is larger than the states in the table",
+                count
+            )?,
+        }
+        write!(f, " at line {}", self.line())
+    }
+}
+
+#[cfg(not(feature = "no_std"))]
+impl std::error::Error for TableError {}
+
+/// The error of a match that needed more steps than its budget allows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BudgetExceeded {
+    pub max_steps: usize,
+}
+
+impl Display for BudgetExceeded {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Exceeded the budget of {} steps", self.max_steps)
+    }
+}
+
+#[cfg(not(feature = "no_std"))]
+impl std::error::Error for BudgetExceeded {}
@@ -1,5 +1,8 @@
-use std::fmt::Display;
-use std::str::Chars;
+use crate::error::RegexError;
+use crate::prelude::*;
+use core::fmt::Display;
+use core::iter::Peekable;
+use core::str::Chars;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Token {
@@ -7,85 +10,497 @@ pub enum Token {
     UnionOperator,
     StarOperator,
     PlusOperator,
+    QuestionOperator,
+    Dot,
+    Caret,
+    Dollar,
     LeftParen,
     RightParen,
+    LeftBracket,
+    RightBracket,
+    Hyphen,
+    LeftBrace,
+    RightBrace,
+    Comma,
+    Digit(u32),
+    ClassEscape(char),
     EndOfFile,
 }
 
 impl Display for Token {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         let str = match self {
             Token::Character(_) => "Character",
             Token::UnionOperator => "|",
             Token::StarOperator => "*",
             Token::PlusOperator => "+",
+            Token::QuestionOperator => "?",
+            Token::Dot => ".",
+            Token::Caret => "^",
+            Token::Dollar => "$",
             Token::LeftParen => "(",
             Token::RightParen => ")",
+            Token::LeftBracket => "[",
+            Token::RightBracket => "]",
+            Token::Hyphen => "-",
+            Token::LeftBrace => "{",
+            Token::RightBrace => "}",
+            Token::Comma => ",",
+            Token::Digit(_) => "Digit",
+            Token::ClassEscape(_) => "ClassEscape",
             Token::EndOfFile => "EOF",
         };
         write!(f, "{}", str)
     }
 }
 
-pub struct Lexer<'a> {
-    string: Chars<'a>,
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode {
+    Normal,
+    Class,
+    Repetition,
+}
+
+type Result<T> = core::result::Result<T, RegexError>;
+
+/// The characters that are not literal outside of a character class, plus `]` and `}`.
+/// Escaping any of them with `\` makes it literal.
+pub const METACHARACTERS: &[char] = &[
+    '\\', '|', '*', '+', '?', '.', '^', '$', '(', ')', '[', ']', '{', '}',
+];
+
+/// Returns the pattern matching `text` literally.
+pub fn escape(text: &str) -> String {
+    let mut ret = String::with_capacity(text.len());
+    for char in text.chars() {
+        if METACHARACTERS.contains(&char) {
+            ret.push('\\');
+        }
+        ret.push(char);
+    }
+    ret
+}
+
+const BACKREFERENCE: &str = "backreferences are not regular";
+const LOOKAROUND: &str = "lookaround is not regular";
+const NAMED_GROUP: &str = "named groups are not supported, use '(' or '(?:' instead";
+const NEGATED_CLASS: &str =
+    "negated classes are not supported, escape '^' as '\\^' for a literal one";
+
+pub struct Lexer<I: Iterator<Item = char>> {
+    string: Peekable<I>,
+    mode: Mode,
+    position: usize,
+    token_start: usize,
+}
+
+impl<'a> Lexer<Chars<'a>> {
+    pub fn new(string: &'a str) -> Self {
+        Lexer::from_chars(string.chars())
+    }
 }
 
-impl Lexer<'_> {
-    pub fn new(string: &str) -> Lexer {
+impl<I: Iterator<Item = char>> Lexer<I> {
+    /// Lexes the pattern given as characters, e.g. after pre-processing it. Spans are still
+    /// byte offsets into the UTF-8 encoding of those characters.
+    pub fn from_chars(chars: I) -> Self {
         Lexer {
-            string: string.chars(),
+            string: chars.peekable(),
+            mode: Mode::Normal,
+            position: 0,
+            token_start: 0,
         }
     }
 
-    pub fn scan(&mut self) -> Token {
-        let Some(char) = self.string.next() else {
-            return Token::EndOfFile
+    /// Returns the byte offset of the last scanned token.
+    pub fn token_start(&self) -> usize {
+        self.token_start
+    }
+
+    pub fn scan(&mut self) -> Result<Token> {
+        self.token_start = self.position;
+        let Some(char) = self.next_char() else {
+            return Ok(Token::EndOfFile);
         };
-        match char {
-            '\\' => Token::Character(self.string.next().unwrap()),
+        match self.mode {
+            Mode::Normal => self.scan_normal(char),
+            Mode::Class => self.scan_class(char),
+            Mode::Repetition => self.scan_repetition(char),
+        }
+    }
+
+    fn scan_normal(&mut self, char: char) -> Result<Token> {
+        Ok(match char {
+            '\\' => self.scan_escape()?,
+            '[' => {
+                // `[^...]` would silently be a class containing '^' otherwise.
+                if self.string.peek() == Some(&'^') {
+                    return Err(self.unsupported(NEGATED_CLASS));
+                }
+                self.mode = Mode::Class;
+                Token::LeftBracket
+            }
+            '{' => {
+                self.mode = Mode::Repetition;
+                Token::LeftBrace
+            }
             '|' => Token::UnionOperator,
-            '(' => Token::LeftParen,
+            '(' => self.scan_group()?,
             ')' => Token::RightParen,
             '*' => Token::StarOperator,
             '+' => Token::PlusOperator,
+            '?' => Token::QuestionOperator,
+            '.' => Token::Dot,
+            '^' => Token::Caret,
+            '$' => Token::Dollar,
             _ => Token::Character(char),
+        })
+    }
+
+    /// Inside a character class, every character except `]`, `-` and `\` is literal.
+    fn scan_class(&mut self, char: char) -> Result<Token> {
+        Ok(match char {
+            '\\' => self.scan_escape()?,
+            ']' => {
+                self.mode = Mode::Normal;
+                Token::RightBracket
+            }
+            '-' => Token::Hyphen,
+            _ => Token::Character(char),
+        })
+    }
+
+    /// `\d`, `\w`, `\s` and their negations are class escapes, `\n`, `\t`, `\r` and `\0` are
+    /// control characters, `\xNN` and `\u{NNNN}` are hexadecimal code points, others are literal
+    /// characters.
+    fn scan_escape(&mut self) -> Result<Token> {
+        let Some(char) = self.next_char() else {
+            return Err(RegexError::TrailingEscape {
+                span: self.token_start,
+            });
+        };
+        Ok(match char {
+            char @ ('d' | 'w' | 's' | 'D' | 'W' | 'S') => Token::ClassEscape(char),
+            'n' => Token::Character('\n'),
+            't' => Token::Character('\t'),
+            'r' => Token::Character('\r'),
+            '0' => Token::Character('\0'),
+            'x' => Token::Character(self.scan_hex()?),
+            'u' => Token::Character(self.scan_unicode()?),
+            '1'..='9' | 'k' => return Err(self.unsupported(BACKREFERENCE)),
+            char => Token::Character(char),
+        })
+    }
+
+    /// hex := HexDigit HexDigit
+    fn scan_hex(&mut self) -> Result<char> {
+        let digits = [self.next_char(), self.next_char()]
+            .into_iter()
+            .flatten()
+            .collect::<String>();
+        if digits.len() != 2 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(self.invalid_escape(format!("\\x{}", digits)));
+        }
+        Ok(char::from_u32(u32::from_str_radix(&digits, 16).unwrap()).unwrap())
+    }
+
+    /// unicode := '{' HexDigit+ '}'
+    fn scan_unicode(&mut self) -> Result<char> {
+        if self.next_char() != Some('{') {
+            return Err(self.invalid_escape("\\u".to_string()));
+        }
+        let mut digits = String::new();
+        loop {
+            match self.next_char() {
+                Some('}') => break,
+                Some(char) => digits.push(char),
+                None => return Err(self.invalid_escape(format!("\\u{{{}", digits))),
+            }
+        }
+        let error = || self.invalid_escape(format!("\\u{{{}}}", digits));
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(error());
+        }
+        u32::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(error)
+    }
+
+    /// `(?:` opens a group just like `(`. Lookaround and named groups are reported as
+    /// unsupported, and any other `(?` form is rejected.
+    fn scan_group(&mut self) -> Result<Token> {
+        if self.string.peek() != Some(&'?') {
+            return Ok(Token::LeftParen);
+        }
+        self.next_char();
+        let char = self.next_char();
+        let reason = match (char, self.string.peek()) {
+            (Some(':'), _) => return Ok(Token::LeftParen),
+            (Some('=' | '!'), _) => LOOKAROUND,
+            (Some('<'), Some('=' | '!')) => LOOKAROUND,
+            (Some('<' | '\'' | 'P'), _) => NAMED_GROUP,
+            _ => {
+                return Err(RegexError::UnsupportedGroup {
+                    group: format!("(?{}", char.map(String::from).unwrap_or_default()),
+                    span: self.token_start,
+                })
+            }
+        };
+        Err(self.unsupported(reason))
+    }
+
+    fn unsupported(&self, reason: &'static str) -> RegexError {
+        RegexError::Unsupported {
+            reason,
+            span: self.token_start,
         }
     }
+
+    fn invalid_escape(&self, escape: String) -> RegexError {
+        RegexError::InvalidEscape {
+            escape,
+            span: self.token_start,
+        }
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let char = self.string.next()?;
+        self.position += char.len_utf8();
+        Some(char)
+    }
+
+    /// Inside a repetition, only digits and `,` are meaningful until the closing `}`.
+    fn scan_repetition(&mut self, char: char) -> Result<Token> {
+        Ok(match char {
+            '}' => {
+                self.mode = Mode::Normal;
+                Token::RightBrace
+            }
+            ',' => Token::Comma,
+            _ => match char.to_digit(10) {
+                Some(digit) => Token::Digit(digit),
+                None => Token::Character(char),
+            },
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::error::*;
     use crate::lexer::*;
 
     #[test]
     fn scan() {
         let mut lexer = Lexer::new(r"a|(bc)*");
-        assert_eq!(lexer.scan(), Token::Character('a'));
-        assert_eq!(lexer.scan(), Token::UnionOperator);
-        assert_eq!(lexer.scan(), Token::LeftParen);
-        assert_eq!(lexer.scan(), Token::Character('b'));
-        assert_eq!(lexer.scan(), Token::Character('c'));
-        assert_eq!(lexer.scan(), Token::RightParen);
-        assert_eq!(lexer.scan(), Token::StarOperator);
-        assert_eq!(lexer.scan(), Token::EndOfFile);
+        assert_eq!(lexer.scan(), Ok(Token::Character('a')));
+        assert_eq!(lexer.scan(), Ok(Token::UnionOperator));
+        assert_eq!(lexer.scan(), Ok(Token::LeftParen));
+        assert_eq!(lexer.scan(), Ok(Token::Character('b')));
+        assert_eq!(lexer.scan(), Ok(Token::Character('c')));
+        assert_eq!(lexer.scan(), Ok(Token::RightParen));
+        assert_eq!(lexer.scan(), Ok(Token::StarOperator));
+        assert_eq!(lexer.scan(), Ok(Token::EndOfFile));
     }
 
     #[test]
     fn scan_with_escape() {
         let mut lexer = Lexer::new(r"a|\|\\(\)");
-        assert_eq!(lexer.scan(), Token::Character('a'));
-        assert_eq!(lexer.scan(), Token::UnionOperator);
-        assert_eq!(lexer.scan(), Token::Character('|'));
-        assert_eq!(lexer.scan(), Token::Character('\\'));
-        assert_eq!(lexer.scan(), Token::LeftParen);
-        assert_eq!(lexer.scan(), Token::Character(')'));
-        assert_eq!(lexer.scan(), Token::EndOfFile);
+        assert_eq!(lexer.scan(), Ok(Token::Character('a')));
+        assert_eq!(lexer.scan(), Ok(Token::UnionOperator));
+        assert_eq!(lexer.scan(), Ok(Token::Character('|')));
+        assert_eq!(lexer.scan(), Ok(Token::Character('\\')));
+        assert_eq!(lexer.scan(), Ok(Token::LeftParen));
+        assert_eq!(lexer.scan(), Ok(Token::Character(')')));
+        assert_eq!(lexer.scan(), Ok(Token::EndOfFile));
+    }
+
+    #[test]
+    fn scan_plus() {
+        let mut lexer = Lexer::new(r"a+\+");
+        assert_eq!(lexer.scan(), Ok(Token::Character('a')));
+        assert_eq!(lexer.scan(), Ok(Token::PlusOperator));
+        assert_eq!(lexer.scan(), Ok(Token::Character('+')));
+        assert_eq!(lexer.scan(), Ok(Token::EndOfFile));
+    }
+
+    #[test]
+    fn scan_question() {
+        let mut lexer = Lexer::new(r"a?\?");
+        assert_eq!(lexer.scan(), Ok(Token::Character('a')));
+        assert_eq!(lexer.scan(), Ok(Token::QuestionOperator));
+        assert_eq!(lexer.scan(), Ok(Token::Character('?')));
+        assert_eq!(lexer.scan(), Ok(Token::EndOfFile));
+    }
+
+    #[test]
+    fn scan_dot() {
+        let mut lexer = Lexer::new(r"a.\.");
+        assert_eq!(lexer.scan(), Ok(Token::Character('a')));
+        assert_eq!(lexer.scan(), Ok(Token::Dot));
+        assert_eq!(lexer.scan(), Ok(Token::Character('.')));
+        assert_eq!(lexer.scan(), Ok(Token::EndOfFile));
+    }
+
+    #[test]
+    fn scan_class() {
+        let mut lexer = Lexer::new(r"[a|(\]]*");
+        assert_eq!(lexer.scan(), Ok(Token::LeftBracket));
+        assert_eq!(lexer.scan(), Ok(Token::Character('a')));
+        assert_eq!(lexer.scan(), Ok(Token::Character('|')));
+        assert_eq!(lexer.scan(), Ok(Token::Character('(')));
+        assert_eq!(lexer.scan(), Ok(Token::Character(']')));
+        assert_eq!(lexer.scan(), Ok(Token::RightBracket));
+        assert_eq!(lexer.scan(), Ok(Token::StarOperator));
+        assert_eq!(lexer.scan(), Ok(Token::EndOfFile));
+    }
+
+    #[test]
+    fn scan_negated_class() {
+        let mut lexer = Lexer::new(r"a[^b]");
+        assert_eq!(lexer.scan(), Ok(Token::Character('a')));
+        assert!(matches!(
+            lexer.scan(),
+            Err(RegexError::Unsupported { span: 1, .. })
+        ));
+        let mut lexer = Lexer::new(r"[\^b^]");
+        assert_eq!(lexer.scan(), Ok(Token::LeftBracket));
+        assert_eq!(lexer.scan(), Ok(Token::Character('^')));
+        assert_eq!(lexer.scan(), Ok(Token::Character('b')));
+        assert_eq!(lexer.scan(), Ok(Token::Character('^')));
+        assert_eq!(lexer.scan(), Ok(Token::RightBracket));
+    }
+
+    #[test]
+    fn scan_class_range() {
+        let mut lexer = Lexer::new(r"-[a-c\-]");
+        assert_eq!(lexer.scan(), Ok(Token::Character('-')));
+        assert_eq!(lexer.scan(), Ok(Token::LeftBracket));
+        assert_eq!(lexer.scan(), Ok(Token::Character('a')));
+        assert_eq!(lexer.scan(), Ok(Token::Hyphen));
+        assert_eq!(lexer.scan(), Ok(Token::Character('c')));
+        assert_eq!(lexer.scan(), Ok(Token::Character('-')));
+        assert_eq!(lexer.scan(), Ok(Token::RightBracket));
+        assert_eq!(lexer.scan(), Ok(Token::EndOfFile));
+    }
+
+    #[test]
+    fn scan_repetition() {
+        let mut lexer = Lexer::new(r"a{2,13}1,");
+        assert_eq!(lexer.scan(), Ok(Token::Character('a')));
+        assert_eq!(lexer.scan(), Ok(Token::LeftBrace));
+        assert_eq!(lexer.scan(), Ok(Token::Digit(2)));
+        assert_eq!(lexer.scan(), Ok(Token::Comma));
+        assert_eq!(lexer.scan(), Ok(Token::Digit(1)));
+        assert_eq!(lexer.scan(), Ok(Token::Digit(3)));
+        assert_eq!(lexer.scan(), Ok(Token::RightBrace));
+        assert_eq!(lexer.scan(), Ok(Token::Character('1')));
+        assert_eq!(lexer.scan(), Ok(Token::Character(',')));
+        assert_eq!(lexer.scan(), Ok(Token::EndOfFile));
+    }
+
+    #[test]
+    fn scan_class_escape() {
+        let mut lexer = Lexer::new(r"\d\W\\d[\s]");
+        assert_eq!(lexer.scan(), Ok(Token::ClassEscape('d')));
+        assert_eq!(lexer.scan(), Ok(Token::ClassEscape('W')));
+        assert_eq!(lexer.scan(), Ok(Token::Character('\\')));
+        assert_eq!(lexer.scan(), Ok(Token::Character('d')));
+        assert_eq!(lexer.scan(), Ok(Token::LeftBracket));
+        assert_eq!(lexer.scan(), Ok(Token::ClassEscape('s')));
+        assert_eq!(lexer.scan(), Ok(Token::RightBracket));
+        assert_eq!(lexer.scan(), Ok(Token::EndOfFile));
+    }
+
+    #[test]
+    fn scan_control_escape() {
+        let mut lexer = Lexer::new(r"\t\n\r\0\c[\t]");
+        assert_eq!(lexer.scan(), Ok(Token::Character('\t')));
+        assert_eq!(lexer.scan(), Ok(Token::Character('\n')));
+        assert_eq!(lexer.scan(), Ok(Token::Character('\r')));
+        assert_eq!(lexer.scan(), Ok(Token::Character('\0')));
+        assert_eq!(lexer.scan(), Ok(Token::Character('c')));
+        assert_eq!(lexer.scan(), Ok(Token::LeftBracket));
+        assert_eq!(lexer.scan(), Ok(Token::Character('\t')));
+        assert_eq!(lexer.scan(), Ok(Token::RightBracket));
+        assert_eq!(lexer.scan(), Ok(Token::EndOfFile));
+    }
+
+    #[test]
+    fn scan_hex_escape() {
+        let mut lexer = Lexer::new(r"\x61\x7E[\x2d]");
+        assert_eq!(lexer.scan(), Ok(Token::Character('a')));
+        assert_eq!(lexer.scan(), Ok(Token::Character('~')));
+        assert_eq!(lexer.scan(), Ok(Token::LeftBracket));
+        assert_eq!(lexer.scan(), Ok(Token::Character('-')));
+        assert_eq!(lexer.scan(), Ok(Token::RightBracket));
+        assert_eq!(lexer.scan(), Ok(Token::EndOfFile));
+        assert_eq!(
+            Lexer::new(r"\xZZ").scan(),
+            Err(RegexError::InvalidEscape {
+                escape: r"\xZZ".to_string(),
+                span: 0
+            })
+        );
+        assert!(Lexer::new(r"\x4").scan().is_err());
+        assert!(Lexer::new(r"\x+1").scan().is_err());
+    }
+
+    #[test]
+    fn scan_unicode_escape() {
+        let mut lexer = Lexer::new(r"\u{5C71}\u{1F600}\u{61}");
+        assert_eq!(lexer.scan(), Ok(Token::Character('山')));
+        assert_eq!(lexer.scan(), Ok(Token::Character('😀')));
+        assert_eq!(lexer.scan(), Ok(Token::Character('a')));
+        assert_eq!(lexer.scan(), Ok(Token::EndOfFile));
+        assert!(Lexer::new(r"\u{D800}").scan().is_err());
+        assert!(Lexer::new(r"\u{110000}").scan().is_err());
+        assert!(Lexer::new(r"\u{}").scan().is_err());
+        assert!(Lexer::new(r"\u{+61}").scan().is_err());
+        assert!(Lexer::new(r"\u{61").scan().is_err());
+        assert!(Lexer::new(r"\u61").scan().is_err());
+    }
+
+    #[test]
+    fn scan_trailing_escape() {
+        let mut lexer = Lexer::new(r"a\");
+        assert_eq!(lexer.scan(), Ok(Token::Character('a')));
+        assert_eq!(lexer.scan(), Err(RegexError::TrailingEscape { span: 1 }));
+        assert!(Lexer::new(r"[\")
+            .scan()
+            .is_ok_and(|t| t == Token::LeftBracket));
+    }
+
+    #[test]
+    fn token_start() {
+        let mut lexer = Lexer::new(r"a山\u{1F600}[\d]");
+        let mut starts = vec![];
+        while lexer.scan() != Ok(Token::EndOfFile) {
+            starts.push(lexer.token_start());
+        }
+        assert_eq!(starts, vec![0, 1, 4, 13, 14, 16]);
+        assert_eq!(lexer.token_start(), 17);
     }
 
     #[test]
     fn with_empty() {
         let mut lexer = Lexer::new(r#""#);
-        assert_eq!(lexer.scan(), Token::EndOfFile);
+        assert_eq!(lexer.scan(), Ok(Token::EndOfFile));
+    }
+
+    #[test]
+    fn from_chars() {
+        let chars = r"(?:a|b)*\+".chars().collect::<Vec<_>>();
+        let mut lexer = Lexer::from_chars(chars.into_iter());
+        assert_eq!(lexer.scan(), Ok(Token::LeftParen));
+        assert_eq!(lexer.scan(), Ok(Token::Character('a')));
+        assert_eq!(lexer.scan(), Ok(Token::UnionOperator));
+        assert_eq!(lexer.scan(), Ok(Token::Character('b')));
+        assert_eq!(lexer.scan(), Ok(Token::RightParen));
+        assert_eq!(lexer.scan(), Ok(Token::StarOperator));
+        assert_eq!(lexer.scan(), Ok(Token::Character('+')));
+        assert_eq!(lexer.scan(), Ok(Token::EndOfFile));
+        assert_eq!(lexer.token_start(), 10);
     }
 }
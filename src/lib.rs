@@ -1,34 +1,1278 @@
+#![cfg_attr(all(feature = "no_std", not(test)), no_std)]
+
+extern crate alloc;
+
 mod automaton;
+mod builder;
+mod error;
 mod lexer;
+mod matcher;
+mod multi;
+mod normalize;
 mod parser;
+mod prelude;
+
+pub use automaton::{
+    Automaton, DFAState, DeterministicFiniteAutomaton, NFAState, NondeterministicFiniteAutomaton,
+};
+#[cfg(feature = "unicode-normalization")]
+pub use builder::Normalization;
+pub use builder::{Backend, RegexBuilder};
+pub use error::{BudgetExceeded, DeserializeError, RegexError, TableError};
+pub use matcher::Matcher;
+pub use multi::MultiRegex;
+pub use parser::Node;
 
+use alloc::borrow::Cow;
 use automaton::*;
 use lexer::*;
+use normalize::*;
 use parser::*;
+use prelude::*;
 
+/// A compiled regex. It is `Send` and `Sync`, so it can be shared across threads in an `Arc`
+/// instead of being cloned, which copies the automata.
+#[derive(Clone)]
 pub struct Regex {
-    dfa: DeterministicFiniteAutomaton,
+    /// The engine for matching the whole text.
+    engine: Engine,
+    /// The engines for a match in a larger text, only if the pattern has `^` or `$`, which then
+    /// depend on where the match is.
+    anchored: Option<Box<Anchored>>,
+    /// tags[state] = The index of the first alternative that matches a text leading `engine` to
+    /// `state`, only if this regex is built by [`Regex::new_tagged`].
+    tags: Option<HashMap<DFAState, usize>>,
+    /// The normalization applied to every text given to the regex.
+    #[cfg(feature = "unicode-normalization")]
+    normalization: Normalization,
+    /// True if a match found in a larger text must be bounded by non-word characters.
+    whole_word: bool,
+}
+
+/// The engines for the matches that do not cover the whole text, by where they are in it.
+#[derive(Clone)]
+struct Anchored {
+    /// A match at the start of the text, ending before its end.
+    start: Engine,
+    /// A match at the end of the text, starting after its start.
+    end: Engine,
+    /// A match starting after the start of the text and ending before its end.
+    inner: Engine,
+}
+
+#[derive(Clone)]
+enum Engine {
+    Dfa(DeterministicFiniteAutomaton),
+    /// Simulates the NFA directly, to avoid the state explosion of the subset construction.
+    Nfa(NondeterministicFiniteAutomaton),
+    /// Builds the DFA while matching, only as far as the inputs require.
+    Lazy(LazyDeterministicFiniteAutomaton),
+}
+
+/// A state of an `Engine`, the lazy DFA sharing the states of the DFA.
+#[derive(PartialEq)]
+enum EngineState {
+    Dfa(DFAState),
+    Nfa(Vec<NFAState>),
+}
+
+/// Parses `pattern` into its AST without compiling it.
+pub fn parse(pattern: &str) -> Result<Node, RegexError> {
+    let parser = &mut Parser::new(Lexer::new(pattern))?;
+    parser.parse()
 }
 
 impl Regex {
-    pub fn new(pattern: &str) -> Result<Regex, String> {
-        let parser = &mut Parser::new(Lexer::new(pattern));
-        let node = parser.parse()?;
-        let nfa = NondeterministicFiniteAutomaton::from_node(node);
-        let dfa = DeterministicFiniteAutomaton::from_nfa(nfa);
-        Ok(Regex { dfa })
+    pub fn new(pattern: &str) -> Result<Regex, RegexError> {
+        RegexBuilder::new(pattern).build()
+    }
+
+    /// Compiles a regex that also matches the case variants of every character.
+    /// See [`RegexBuilder::case_insensitive`].
+    pub fn new_case_insensitive(pattern: &str) -> Result<Regex, RegexError> {
+        RegexBuilder::new(pattern).case_insensitive(true).build()
+    }
+
+    /// Compiles a regex that is matched by simulating the NFA instead of building the DFA.
+    /// Matching is slower, but compiling never blows up.
+    pub fn new_nfa(pattern: &str) -> Result<Regex, RegexError> {
+        RegexBuilder::new(pattern).backend(Backend::Nfa).build()
+    }
+
+    /// Compiles a regex whose DFA is built incrementally while matching.
+    /// Only the states the inputs actually visit are computed, and they are cached for later calls.
+    pub fn new_lazy(pattern: &str) -> Result<Regex, RegexError> {
+        RegexBuilder::new(pattern).backend(Backend::LazyDfa).build()
+    }
+
+    /// Compiles the union of `alternatives`, keeping track of which of them matched, see
+    /// [`Regex::match_tag`].
+    pub fn new_tagged(alternatives: &[&str]) -> Result<Regex, RegexError> {
+        let nodes = alternatives
+            .iter()
+            .map(|pattern| parse(pattern))
+            .collect::<Result<Vec<_>, _>>()?;
+        RegexBuilder::new("").build_tagged(nodes)
+    }
+
+    /// Returns the pattern matching `text` literally, by escaping every metacharacter with `\`.
+    pub fn escape(text: &str) -> String {
+        lexer::escape(text)
+    }
+
+    /// Compiles a regex from an AST without parsing a pattern.
+    pub fn from_node(node: Node) -> Regex {
+        // Without `max_states`, building never fails.
+        RegexBuilder::new("").build_node(node).unwrap()
+    }
+
+    fn from_dfa(dfa: DeterministicFiniteAutomaton) -> Regex {
+        Regex {
+            engine: Engine::Dfa(dfa),
+            anchored: None,
+            tags: None,
+            #[cfg(feature = "unicode-normalization")]
+            normalization: Normalization::None,
+            whole_word: false,
+        }
+    }
+
+    /// Returns the NFA, converting the DFA into one if this regex does not hold the NFA.
+    fn nfa(&self) -> Cow<'_, NondeterministicFiniteAutomaton> {
+        match &self.engine {
+            Engine::Dfa(dfa) => Cow::Owned(NondeterministicFiniteAutomaton::from_dfa(dfa)),
+            Engine::Nfa(nfa) => Cow::Borrowed(nfa),
+            Engine::Lazy(lazy) => Cow::Borrowed(lazy.nfa()),
+        }
+    }
+
+    /// Returns the complete DFA, building it first if this regex does not hold it.
+    ///
+    /// ```
+    /// use dfa_regex::{Automaton, Regex};
+    ///
+    /// let regex = Regex::new(r"ab*").unwrap();
+    /// let dfa = regex.dfa();
+    /// let state = dfa.next_state(dfa.start(), 'a').unwrap();
+    /// assert!(!dfa.accepts().contains(&dfa.start()));
+    /// assert!(dfa.is_accept(&state));
+    /// assert_eq!(dfa.next_state(state, 'c'), None);
+    /// ```
+    pub fn dfa(&self) -> Cow<'_, DeterministicFiniteAutomaton> {
+        self.engine.dfa()
+    }
+
+    /// Returns the DFAs for a match of the whole text, at its start, at its end and inside it,
+    /// which are the same unless the pattern has `^` or `$`. See [`Anchored`].
+    fn located_dfas(&self) -> [Cow<'_, DeterministicFiniteAutomaton>; 4] {
+        match &self.anchored {
+            Some(anchored) => [
+                self.engine.dfa(),
+                anchored.start.dfa(),
+                anchored.end.dfa(),
+                anchored.inner.dfa(),
+            ],
+            None => {
+                let dfa = self.engine.dfa();
+                [dfa.clone(), dfa.clone(), dfa.clone(), dfa]
+            }
+        }
+    }
+
+    /// The inverse of `located_dfas`.
+    fn from_located_dfas([whole, start, end, inner]: [DeterministicFiniteAutomaton; 4]) -> Regex {
+        let mut regex = Regex::from_dfa(whole);
+        regex.anchored = Some(Box::new(Anchored {
+            start: Engine::Dfa(start),
+            end: Engine::Dfa(end),
+            inner: Engine::Dfa(inner),
+        }));
+        regex
+    }
+
+    /// Combines the DFAs of both regexes with `f` for each location of a match, so that `^` and
+    /// `$` still only match at the start and the end of the text in a search.
+    fn combine(
+        &self,
+        other: &Regex,
+        f: impl Fn(
+            &DeterministicFiniteAutomaton,
+            &DeterministicFiniteAutomaton,
+        ) -> DeterministicFiniteAutomaton,
+    ) -> Regex {
+        if self.anchored.is_none() && other.anchored.is_none() {
+            return Regex::from_dfa(f(&self.dfa(), &other.dfa()));
+        }
+        let (dfas, others) = (self.located_dfas(), other.located_dfas());
+        Regex::from_located_dfas(core::array::from_fn(|i| f(&dfas[i], &others[i])))
     }
 
+    /// Returns true if this regex matches the whole `text`, i.e. the match is anchored at both
+    /// ends. Use [`Regex::find`] to search for a match anywhere in `text`. The same as
+    /// [`Regex::matches_full`].
     pub fn matches(&self, text: &str) -> bool {
-        let mut current_state = self.dfa.start;
-        for char in text.chars() {
-            if let Some(state) = self.dfa.next_state(current_state, char) {
-                current_state = state;
-            } else {
-                return false;
+        self.matches_full(text)
+    }
+
+    /// Returns true if the whole `text` is consumed and the automaton ends in an accept state,
+    /// so `ab` matches "ab" but not "abc". See [`Regex::matches_prefix`] for the latter.
+    pub fn matches_full(&self, text: &str) -> bool {
+        self.matches_chars(text.chars())
+    }
+
+    /// Returns the index of the first alternative given to [`Regex::new_tagged`] that matches the
+    /// whole `text`, or `None` if none does. A regex built otherwise is a single alternative `0`.
+    pub fn match_tag(&self, text: &str) -> Option<usize> {
+        let normalized = self.normalized(text);
+        let text = &*normalized.text;
+        match (&self.engine, &self.tags) {
+            (Engine::Dfa(dfa), Some(tags)) => {
+                let state = text
+                    .chars()
+                    .try_fold(dfa.start_state(), |state, char| dfa.step(&state, char))?;
+                tags.get(&state).cloned()
+            }
+            _ => self.engine.matches(text.chars()).then_some(0),
+        }
+    }
+
+    /// Returns true if this regex matches some prefix of `text`, i.e. an accept state is reached
+    /// at some point while consuming it, so `ab` matches "abc" as well as "ab". It stops at the
+    /// first such point.
+    pub fn matches_prefix(&self, text: &str) -> bool {
+        self.shortest_match_at(&self.normalized(text).text, 0)
+            .is_some()
+    }
+
+    /// Returns true if this regex matches some suffix of `text`, i.e. the match is anchored at the
+    /// end only.
+    pub fn matches_suffix(&self, text: &str) -> bool {
+        let normalized = self.normalized(text);
+        let text = &*normalized.text;
+        // The engine for the suffixes after the start also matches the fewer strings there.
+        let (engine, whole) = match &self.anchored {
+            Some(anchored) => (&anchored.end, self.engine.matches(text.chars())),
+            None => (&self.engine, false),
+        };
+        whole
+            || match engine {
+                Engine::Dfa(dfa) => matches_suffix(dfa, text),
+                Engine::Nfa(nfa) => matches_suffix(nfa, text),
+                Engine::Lazy(lazy) => matches_suffix(lazy, text),
+            }
+    }
+
+    /// Like [`Regex::matches`], but for a text given as UTF-8 bytes.
+    /// Returns false if `bytes` is not valid UTF-8, as no character is decoded from it.
+    pub fn matches_bytes(&self, bytes: &[u8]) -> bool {
+        core::str::from_utf8(bytes).is_ok_and(|text| self.matches(text))
+    }
+
+    /// Like [`Regex::matches`], but reads the text from `chars`, which is consumed only until
+    /// the regex can no longer match.
+    pub fn matches_chars<I: IntoIterator<Item = char>>(&self, chars: I) -> bool {
+        self.engine
+            .matches(self.normalized_chars(chars.into_iter()))
+    }
+
+    /// Like [`Regex::matches`], but for a text that is already split into characters.
+    pub fn matches_slice(&self, chars: &[char]) -> bool {
+        self.matches_chars(chars.iter().cloned())
+    }
+
+    /// Like [`Regex::matches`], but maps every character of `text` with `mapper` before it is
+    /// matched, e.g. to treat several characters as the same one. The pattern is not mapped, so it
+    /// should be written in terms of the mapped characters.
+    pub fn matches_with_mapper(&self, text: &str, mapper: &impl CharMapper) -> bool {
+        self.matches_chars(text.chars().map(|char| mapper.map(char)))
+    }
+
+    /// Like [`Regex::matches`], but gives up after `max_steps` transitions, one for each character
+    /// read. The work per step is bounded for every backend, so this bounds the time spent on an
+    /// untrusted text.
+    pub fn matches_with_budget(
+        &self,
+        text: &str,
+        max_steps: usize,
+    ) -> Result<bool, BudgetExceeded> {
+        let normalized = self.normalized(text);
+        let text = &*normalized.text;
+        match &self.engine {
+            Engine::Dfa(dfa) => matches_with_budget(dfa, text, max_steps),
+            Engine::Nfa(nfa) => matches_with_budget(nfa, text, max_steps),
+            Engine::Lazy(lazy) => matches_with_budget(lazy, text, max_steps),
+        }
+    }
+
+    /// Runs the automaton over the whole `text`, reporting how far it got instead of only whether
+    /// it matches.
+    pub fn run(&self, text: &str) -> RunResult {
+        let normalized = self.normalized(text);
+        let mut ret = self.engine.run(&normalized.text);
+        if !normalized.is_original() {
+            // The character the automaton is stuck at is mapped to the start of its chunk.
+            ret.consumed = match normalized.text.char_indices().nth(ret.consumed) {
+                Some((i, _)) => text[..normalized.start_in_original(i)].chars().count(),
+                None => text.chars().count(),
+            };
+        }
+        ret
+    }
+
+    /// Alias for [`Regex::matches`].
+    pub fn is_match(&self, text: &str) -> bool {
+        self.matches(text)
+    }
+
+    /// Returns true if this regex matches some substring of `text`, like `find(text).is_some()`,
+    /// but stops as soon as a match is found instead of computing its range.
+    pub fn contains(&self, text: &str) -> bool {
+        let normalized = self.normalized(text);
+        // With `^` or `$`, the engine depends on where the match is, and with `whole_word`, the
+        // match must be bounded by non-word characters, both of which `find_at` takes care of.
+        if self.anchored.is_some() || self.whole_word {
+            return self.find_at(&normalized.text, 0).is_some();
+        }
+        self.engine
+            .first_match_end(normalized.text.chars())
+            .is_some()
+    }
+
+    /// Returns the end of the match that ends first in `chars`, which is not anchored, as the
+    /// number of characters up to it. `chars` is consumed only as far as that end, so a large
+    /// input can be searched without being loaded at once. With `^`, `$` or
+    /// [`RegexBuilder::whole_word`], where a match can be depends on the characters around it, so
+    /// the character after the end is read too. So it is with `RegexBuilder::normalize`, to tell
+    /// whether that character is normalized together with the ones before.
+    pub fn find_in_stream<I: Iterator<Item = char>>(&self, chars: I) -> Option<usize> {
+        let mut chars = self.normalized_chars(chars);
+        if self.anchored.is_none() && !self.whole_word {
+            self.engine.first_match_end(chars.by_ref())?;
+            return Some(chars.read());
+        }
+        // The engines by the location of the match, in the order of `LOCATIONS`. Without `^` and
+        // `$`, the engine for a match not at the end of the text also covers one at its end.
+        let engines = match &self.anchored {
+            Some(anchored) => [
+                Some(&self.engine),
+                Some(&anchored.start),
+                Some(&anchored.end),
+                Some(&anchored.inner),
+            ],
+            None => [None, Some(&self.engine), None, Some(&self.engine)],
+        };
+        // The states reached from every start so far, like in `first_match_end`, which are
+        // advanced one character at a time, knowing only the characters around the position.
+        let mut states: [Vec<EngineState>; 4] = Default::default();
+        let (mut at_start, mut previous, mut read) = (true, None, 0);
+        let mut next = chars.next();
+        loop {
+            let inside_word = previous.is_some_and(is_word_char) && next.is_some_and(is_word_char);
+            let bounded = !self.whole_word || !inside_word;
+            for (i, engine) in engines.iter().enumerate() {
+                let Some(engine) = engine else { continue };
+                let (start, end) = LOCATIONS[i];
+                if bounded && start == at_start {
+                    let state = engine.start_state();
+                    if !states[i].contains(&state) {
+                        states[i].push(state);
+                    }
+                }
+                let accepts = states[i].iter().any(|state| engine.is_accept(state));
+                if bounded && (!end || next.is_none()) && accepts {
+                    return Some(read);
+                }
+            }
+            let char = next?;
+            for (i, engine) in engines.iter().enumerate() {
+                let Some(engine) = engine else { continue };
+                let mut next_states = vec![];
+                for state in states[i]
+                    .iter()
+                    .filter_map(|state| engine.step(state, char))
+                {
+                    if !next_states.contains(&state) {
+                        next_states.push(state);
+                    }
+                }
+                states[i] = next_states;
+            }
+            (at_start, previous, read) = (false, Some(char), chars.read());
+            next = chars.next();
+        }
+    }
+
+    /// Returns the byte offsets in `text` where a match ends, in ascending order. Unlike
+    /// [`Regex::find_iter`], the matches can overlap, so `aa` ends at 2, 3 and 4 in `"aaaa"`.
+    pub fn match_ends(&self, text: &str) -> Vec<usize> {
+        let normalized = self.normalized(text);
+        let mut ret = self.match_ends_in(&normalized.text);
+        if !normalized.is_original() {
+            ret.iter_mut()
+                .for_each(|end| *end = normalized.end_in_original(*end));
+            ret.dedup();
+        }
+        ret
+    }
+
+    /// Returns true if this regex matches a substring of `text` starting at byte offset `start`,
+    /// i.e. the match is anchored at `start` only.
+    ///
+    /// # Panics
+    /// Panics if `start` is not on a character boundary of `text`.
+    pub fn is_match_at(&self, text: &str, start: usize) -> bool {
+        assert!(
+            text.is_char_boundary(start),
+            "{start} is not a character boundary"
+        );
+        let normalized = self.normalized(text);
+        normalized
+            .offset_in_text(start)
+            .is_some_and(|start| self.longest_match_at(&normalized.text, start).is_some())
+    }
+
+    /// Returns a matcher that is fed the text character by character.
+    pub fn matcher(&self) -> Matcher<'_> {
+        Matcher::new(self)
+    }
+
+    /// Returns the byte length of the longest prefix of `text` that this regex matches.
+    pub fn match_len(&self, text: &str) -> Option<usize> {
+        let normalized = self.normalized(text);
+        self.longest_match_at(&normalized.text, 0)
+            .map(|end| normalized.end_in_original(end))
+    }
+
+    /// Splits `text` into the longest prefix this regex matches and the rest, or returns `None`
+    /// if no prefix matches. Unlike [`Regex::find`], the match is anchored at the start.
+    pub fn consume<'t>(&self, text: &'t str) -> Option<(&'t str, &'t str)> {
+        self.match_len(text).map(|len| text.split_at(len))
+    }
+
+    /// Returns the regex matching exactly the strings this regex does not match.
+    pub fn complement(&self) -> Regex {
+        self.combine(self, |dfa, _| dfa.complement())
+    }
+
+    /// Returns the regex matching exactly the strings both regexes match.
+    pub fn intersect(&self, other: &Regex) -> Regex {
+        self.combine(other, |dfa, other| dfa.intersect(other))
+    }
+
+    /// Returns the regex matching exactly the strings one of the regexes matches and the other
+    /// does not. Its [`Regex::shortest_match`] is an example of how they differ.
+    pub fn symmetric_difference(&self, other: &Regex) -> Regex {
+        self.combine(other, |dfa, other| dfa.symmetric_difference(other))
+    }
+
+    /// Returns the shortest string one of the regexes matches and the other does not, or `None` if
+    /// they are equivalent.
+    pub fn difference_witness(&self, other: &Regex) -> Option<String> {
+        self.symmetric_difference(other).shortest_match()
+    }
+
+    /// Returns the regex matching a string this regex matches followed by a string `other`
+    /// matches.
+    pub fn concat(&self, other: &Regex) -> Regex {
+        if self.anchored.is_none() && other.anchored.is_none() {
+            let nfa = self.nfa().concat(&other.nfa());
+            return Regex::from_dfa(DeterministicFiniteAutomaton::from_nfa(nfa));
+        }
+        // A match `uv` where `u` matches this regex and `v` the other one. `u` is at the start of
+        // the text only if the match is, and at its end only if `v` is empty, and vice versa.
+        let (dfas, others) = (self.located_dfas(), other.located_dfas());
+        Regex::from_located_dfas(core::array::from_fn(|i| {
+            let (at_start, at_end) = LOCATIONS[i];
+            let mut ret = concat(
+                &non_empty(&dfas[location(at_start, false)]),
+                &non_empty(&others[location(false, at_end)]),
+            );
+            if accepts_empty(&dfas[location(at_start, false)]) {
+                ret = union(&ret, &others[i]);
+            }
+            if accepts_empty(&others[location(false, at_end)]) {
+                ret = union(&ret, &dfas[i]);
+            }
+            ret
+        }))
+    }
+
+    /// Returns the regex matching the strings either regex matches.
+    pub fn union(&self, other: &Regex) -> Regex {
+        if self.anchored.is_none() && other.anchored.is_none() {
+            let nfa = self.nfa().union(&other.nfa());
+            return Regex::from_dfa(DeterministicFiniteAutomaton::from_nfa(nfa));
+        }
+        self.combine(other, union)
+    }
+
+    /// Returns the regex matching the concatenations of zero or more strings this regex matches.
+    pub fn star(&self) -> Regex {
+        if self.anchored.is_none() {
+            let nfa = self.nfa().star();
+            return Regex::from_dfa(DeterministicFiniteAutomaton::from_nfa(nfa));
+        }
+        // The empty pieces of a match can be left out, as the empty string is matched anyway.
+        // Of the others, only the first one is at the start of the text and the last one at its
+        // end.
+        let dfas = self.located_dfas();
+        let inner = NondeterministicFiniteAutomaton::from_dfa(&non_empty(&dfas[3])).star();
+        let inner = DeterministicFiniteAutomaton::from_nfa(inner);
+        Regex::from_located_dfas(core::array::from_fn(|i| {
+            let (at_start, at_end) = LOCATIONS[i];
+            let first = non_empty(&dfas[location(at_start, false)]);
+            let last = non_empty(&dfas[location(false, at_end)]);
+            let several = concat(&concat(&first, &inner), &last);
+            let empty = DeterministicFiniteAutomaton::from_nfa(
+                NondeterministicFiniteAutomaton::new(NFAState(0), [NFAState(0)].into()),
+            );
+            union(&union(&empty, &dfas[i]), &several)
+        }))
+    }
+
+    /// Returns the regex matching the reversed strings this regex matches.
+    pub fn reverse(&self) -> Regex {
+        let reverse = |dfa: &DeterministicFiniteAutomaton| {
+            let nfa = NondeterministicFiniteAutomaton::from_dfa(dfa).reverse();
+            DeterministicFiniteAutomaton::from_nfa(nfa)
+        };
+        if self.anchored.is_none() {
+            return Regex::from_dfa(reverse(&self.dfa()));
+        }
+        // The start of the text becomes its end, and vice versa.
+        let [whole, start, end, inner] = self.located_dfas();
+        Regex::from_located_dfas([
+            reverse(&whole),
+            reverse(&end),
+            reverse(&start),
+            reverse(&inner),
+        ])
+    }
+
+    /// Returns true if both regexes match exactly the same strings.
+    pub fn equivalent(&self, other: &Regex) -> bool {
+        self.dfa().equivalent(&other.dfa())
+    }
+
+    /// Returns true if every string this regex matches is matched by `other`, too.
+    pub fn is_subset_of(&self, other: &Regex) -> bool {
+        self.dfa().is_subset_of(&other.dfa())
+    }
+
+    /// Returns true if this regex matches no string at all.
+    pub fn is_empty_language(&self) -> bool {
+        self.dfa().is_empty()
+    }
+
+    /// Returns true if this regex matches infinitely many strings.
+    pub fn is_infinite(&self) -> bool {
+        self.dfa().is_infinite()
+    }
+
+    /// Returns the number of strings of exactly `n` characters this regex matches, or `u64::MAX`
+    /// if there are more. The characters are all `char`s, so e.g. `.` matches 1,112,063 strings
+    /// of length 1, which are all but "\n", and 1,112,064 with
+    /// [`RegexBuilder::dot_matches_newline`].
+    ///
+    /// ```
+    /// use dfa_regex::{Regex, RegexBuilder};
+    ///
+    /// assert_eq!(Regex::new(r".").unwrap().count_matches_of_length(1), 1_112_063);
+    /// let regex = RegexBuilder::new(r".").dot_matches_newline(true).build().unwrap();
+    /// assert_eq!(regex.count_matches_of_length(1), 1_112_064);
+    /// ```
+    pub fn count_matches_of_length(&self, n: usize) -> u64 {
+        self.dfa().count_strings_of_length(n)
+    }
+
+    /// Returns the shortest string this regex matches, or `None` if it matches nothing.
+    pub fn shortest_match(&self) -> Option<String> {
+        self.dfa().shortest_string()
+    }
+
+    /// Returns the literal prefix every string this regex matches starts with, or `None` if there
+    /// is none. A match can only start where `text` contains the prefix, so it can be found with a
+    /// substring search before running the automaton.
+    pub fn literal_prefix(&self) -> Option<String> {
+        Some(self.dfa().literal_prefix()).filter(|prefix| !prefix.is_empty())
+    }
+
+    /// Returns a random string of at most `max_len` characters this regex matches, or `None` if
+    /// there is none.
+    #[cfg(feature = "rand")]
+    pub fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R, max_len: usize) -> Option<String> {
+        self.dfa().sample(rng, max_len)
+    }
+
+    /// Returns the characters the compiled DFA has a transition of its own for. Every other
+    /// character takes the default transitions, e.g. of `.`.
+    pub fn alphabet(&self) -> alloc::collections::BTreeSet<char> {
+        self.dfa().alphabet()
+    }
+
+    /// Returns the number of states of the compiled DFA.
+    /// The complete DFA is built on demand if this regex does not hold it.
+    pub fn state_count(&self) -> usize {
+        self.dfa().state_count() as usize
+    }
+
+    /// Returns the size of the compiled DFA, building it first if this regex does not hold it.
+    pub fn stats(&self) -> RegexStats {
+        let dfa = self.dfa();
+        RegexStats {
+            num_states: dfa.state_count() as usize,
+            num_transitions: dfa.transitions().count() + dfa.default_transitions().count(),
+            num_accept_states: dfa.accepts().len(),
+            alphabet_size: dfa.alphabet().len(),
+            is_minimal: dfa.minimize().state_count() == dfa.state_count(),
+        }
+    }
+
+    /// Returns the compiled DFA in a binary format, which [`Regex::from_bytes`] reads back.
+    /// Only the DFA for matching the whole text is written, so the read regex treats `^` and `$`
+    /// as matching at the start and the end of any match when searching.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.dfa().serialize()
+    }
+
+    /// Reads a regex written by [`Regex::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Regex, DeserializeError> {
+        Ok(Regex::from_dfa(DeterministicFiniteAutomaton::deserialize(
+            bytes,
+        )?))
+    }
+
+    /// Returns the compiled DFA as a flat table of transitions.
+    pub fn to_table(&self) -> String {
+        self.dfa().to_table()
+    }
+
+    /// Reads a regex from a table of transitions in the format of [`Regex::to_table`].
+    pub fn from_table(text: &str) -> Result<Regex, TableError> {
+        Ok(Regex::from_dfa(DeterministicFiniteAutomaton::from_table(
+            text,
+        )?))
+    }
+
+    /// Returns the compiled DFA in the Graphviz DOT format.
+    pub fn to_dot(&self) -> String {
+        self.dfa().to_dot()
+    }
+
+    /// Returns the byte range of the leftmost-longest match in `text`, which is not anchored.
+    ///
+    /// Among the matches starting at the leftmost position, the longest one is returned as in
+    /// POSIX, so `a|ab` finds `ab` in `"ab"`, not `a`.
+    pub fn find(&self, text: &str) -> Option<(usize, usize)> {
+        self.find_iter(text).next()
+    }
+
+    /// Like [`Regex::find`], but returns the shortest match starting at the leftmost position,
+    /// so `a+` finds `a` in `"aaa"`.
+    pub fn find_shortest(&self, text: &str) -> Option<(usize, usize)> {
+        let normalized = self.normalized(text);
+        self.find_shortest_in(&normalized.text)
+            .map(|range| normalized.range_in_original(range))
+    }
+
+    /// Returns an iterator over the byte ranges of successive non-overlapping matches in `text`.
+    pub fn find_iter<'r, 't>(&'r self, text: &'t str) -> FindIter<'r, 't> {
+        FindIter {
+            regex: self,
+            text: self.normalized(text),
+            position: 0,
+            last_end: 0,
+        }
+    }
+
+    /// Like [`Regex::find_iter`], but also yields the matched substring of `text`.
+    pub fn match_indices<'r, 't>(
+        &'r self,
+        text: &'t str,
+    ) -> impl Iterator<Item = (usize, usize, &'t str)> + use<'r, 't> {
+        self.find_iter(text)
+            .map(move |(start, end)| (start, end, &text[start..end]))
+    }
+
+    /// Replaces every non-overlapping match in `text` with `replacement`.
+    pub fn replace_all(&self, text: &str, replacement: &str) -> String {
+        let mut ret = String::with_capacity(text.len());
+        let mut last = 0;
+        for (start, end) in self.find_iter(text) {
+            ret.push_str(&text[last..start]);
+            ret.push_str(replacement);
+            last = end;
+        }
+        ret.push_str(&text[last..]);
+        ret
+    }
+
+    /// Returns the substrings of `text` separated by non-overlapping matches.
+    pub fn split<'t>(&self, text: &'t str) -> Vec<&'t str> {
+        let mut ret = vec![];
+        let mut last = 0;
+        for (start, end) in self.find_iter(text) {
+            ret.push(&text[last..start]);
+            last = end;
+        }
+        ret.push(&text[last..]);
+        ret
+    }
+
+    /// Returns the byte range of the leftmost-longest match starting at or after `from`.
+    fn find_at(&self, text: &str, from: usize) -> Option<(usize, usize)> {
+        let mut starts = text[from..]
+            .char_indices()
+            .map(|(i, _)| from + i)
+            .chain([text.len()]);
+        if self.whole_word {
+            // The longest match may end inside a word where a shorter one does not.
+            return starts
+                .filter(|start| is_word_boundary(text, *start))
+                .find_map(|start| {
+                    let mut ends = self.match_ends_at(text, start).into_iter();
+                    ends.rfind(|end| is_word_boundary(text, *end))
+                        .map(|end| (start, end))
+                });
+        }
+        starts.find_map(|start| self.longest_match_at(text, start).map(|end| (start, end)))
+    }
+
+    /// Returns the byte range of the leftmost-shortest match in `text`, which is already
+    /// normalized.
+    fn find_shortest_in(&self, text: &str) -> Option<(usize, usize)> {
+        let mut starts = text.char_indices().map(|(i, _)| i).chain([text.len()]);
+        if self.whole_word {
+            return starts
+                .filter(|start| is_word_boundary(text, *start))
+                .find_map(|start| {
+                    let mut ends = self.match_ends_at(text, start).into_iter();
+                    ends.find(|end| is_word_boundary(text, *end))
+                        .map(|end| (start, end))
+                });
+        }
+        starts.find_map(|start| self.shortest_match_at(text, start).map(|end| (start, end)))
+    }
+
+    /// Returns the ends of the matches in `text`, which is already normalized.
+    fn match_ends_in(&self, text: &str) -> Vec<usize> {
+        if self.anchored.is_none() && !self.whole_word {
+            return self.engine.match_ends(text, 0, true);
+        }
+        // With `^` or `$`, the engine depends on where the match is, and with `whole_word`, the
+        // match must start and end at word boundaries, so each start is tried.
+        let bounded = |i: &usize| !self.whole_word || is_word_boundary(text, *i);
+        let mut ret = alloc::collections::BTreeSet::new();
+        let starts = text.char_indices().map(|(i, _)| i).chain([text.len()]);
+        for start in starts.filter(bounded) {
+            ret.extend(self.match_ends_at(text, start).into_iter().filter(bounded));
+        }
+        ret.into_iter().collect()
+    }
+
+    /// Returns `text` converted to the normalization form of this regex.
+    pub(crate) fn normalized<'t>(&self, text: &'t str) -> Normalized<'t> {
+        #[cfg(feature = "unicode-normalization")]
+        if self.normalization == Normalization::Nfc {
+            return Normalized::nfc(text);
+        }
+        Normalized::original(text)
+    }
+
+    /// Returns `chars` converted to the normalization form of this regex.
+    fn normalized_chars<I: Iterator<Item = char>>(&self, chars: I) -> NormalizedChars<I> {
+        #[cfg(feature = "unicode-normalization")]
+        if self.normalization == Normalization::Nfc {
+            return NormalizedChars::nfc(chars);
+        }
+        NormalizedChars::original(chars)
+    }
+
+    /// Returns the ends of the matches starting at byte offset `start`, in ascending order.
+    fn match_ends_at(&self, text: &str, start: usize) -> Vec<usize> {
+        let Some(anchored) = &self.anchored else {
+            return self.engine.match_ends(text, start, false);
+        };
+        let (inner, end) = match start == 0 {
+            true => (&anchored.start, &self.engine),
+            false => (&anchored.inner, &anchored.end),
+        };
+        let mut ret = inner.match_ends(text, start, false);
+        if ret.last() != Some(&text.len()) && end.matches(text[start..].chars()) {
+            ret.push(text.len());
+        }
+        ret
+    }
+
+    /// Returns the end of the longest match starting at byte offset `start`.
+    fn longest_match_at(&self, text: &str, start: usize) -> Option<usize> {
+        let Some(anchored) = &self.anchored else {
+            return self.engine.longest_match_at(text, start);
+        };
+        // A match ending before the end of the text is found by scanning as usual, and one ending
+        // at the end, where `$` can match, by matching the rest of the text as a whole.
+        let (inner, end) = match start == 0 {
+            true => (&anchored.start, &self.engine),
+            false => (&anchored.inner, &anchored.end),
+        };
+        let ret = inner.longest_match_at(text, start);
+        match ret != Some(text.len()) && end.matches(text[start..].chars()) {
+            true => Some(text.len()),
+            false => ret,
+        }
+    }
+
+    /// Returns the end of the shortest match starting at byte offset `start`.
+    fn shortest_match_at(&self, text: &str, start: usize) -> Option<usize> {
+        let Some(anchored) = &self.anchored else {
+            return self.engine.shortest_match_at(text, start);
+        };
+        // Same as `longest_match_at`, but a match ending at the end of the text is the longest
+        // possible one, so it is only the shortest if no other match is found.
+        let (inner, end) = match start == 0 {
+            true => (&anchored.start, &self.engine),
+            false => (&anchored.inner, &anchored.end),
+        };
+        inner
+            .shortest_match_at(text, start)
+            .or_else(|| end.matches(text[start..].chars()).then_some(text.len()))
+    }
+}
+
+/// Whether a match is at the start and at the end of the text, in the order of `located_dfas`.
+const LOCATIONS: [(bool, bool); 4] = [(true, true), (true, false), (false, true), (false, false)];
+
+/// Returns the index of a location in `LOCATIONS`.
+fn location(at_start: bool, at_end: bool) -> usize {
+    LOCATIONS
+        .iter()
+        .position(|l| *l == (at_start, at_end))
+        .unwrap()
+}
+
+fn accepts_empty(dfa: &DeterministicFiniteAutomaton) -> bool {
+    dfa.accepts().contains(&dfa.start())
+}
+
+/// Returns the DFA matching the non-empty strings `dfa` matches.
+fn non_empty(dfa: &DeterministicFiniteAutomaton) -> DeterministicFiniteAutomaton {
+    let non_empty = NondeterministicFiniteAutomaton::new(NFAState(0), [NFAState(1)].into())
+        .add_any_transition(NFAState(0), NFAState(1))
+        .add_any_transition(NFAState(1), NFAState(1));
+    dfa.intersect(&DeterministicFiniteAutomaton::from_nfa(non_empty))
+}
+
+fn concat(
+    dfa: &DeterministicFiniteAutomaton,
+    other: &DeterministicFiniteAutomaton,
+) -> DeterministicFiniteAutomaton {
+    let nfa = NondeterministicFiniteAutomaton::from_dfa(dfa)
+        .concat(&NondeterministicFiniteAutomaton::from_dfa(other));
+    DeterministicFiniteAutomaton::from_nfa(nfa)
+}
+
+fn union(
+    dfa: &DeterministicFiniteAutomaton,
+    other: &DeterministicFiniteAutomaton,
+) -> DeterministicFiniteAutomaton {
+    let nfa = NondeterministicFiniteAutomaton::from_dfa(dfa)
+        .union(&NondeterministicFiniteAutomaton::from_dfa(other));
+    DeterministicFiniteAutomaton::from_nfa(nfa)
+}
+
+impl Engine {
+    fn start_state(&self) -> EngineState {
+        match self {
+            Engine::Dfa(dfa) => EngineState::Dfa(dfa.start_state()),
+            Engine::Nfa(nfa) => EngineState::Nfa(nfa.start_state()),
+            Engine::Lazy(lazy) => EngineState::Dfa(lazy.start_state()),
+        }
+    }
+
+    fn step(&self, state: &EngineState, char: char) -> Option<EngineState> {
+        match (self, state) {
+            (Engine::Dfa(dfa), EngineState::Dfa(state)) => {
+                dfa.step(state, char).map(EngineState::Dfa)
+            }
+            (Engine::Nfa(nfa), EngineState::Nfa(state)) => {
+                nfa.step(state, char).map(EngineState::Nfa)
+            }
+            (Engine::Lazy(lazy), EngineState::Dfa(state)) => {
+                lazy.step(state, char).map(EngineState::Dfa)
+            }
+            _ => None,
+        }
+    }
+
+    fn is_accept(&self, state: &EngineState) -> bool {
+        match (self, state) {
+            (Engine::Dfa(dfa), EngineState::Dfa(state)) => dfa.is_accept(state),
+            (Engine::Nfa(nfa), EngineState::Nfa(state)) => nfa.is_accept(state),
+            (Engine::Lazy(lazy), EngineState::Dfa(state)) => lazy.is_accept(state),
+            _ => false,
+        }
+    }
+
+    fn dfa(&self) -> Cow<'_, DeterministicFiniteAutomaton> {
+        match self {
+            Engine::Dfa(dfa) => Cow::Borrowed(dfa),
+            Engine::Nfa(nfa) => Cow::Owned(DeterministicFiniteAutomaton::from_nfa(nfa.clone())),
+            Engine::Lazy(lazy) => {
+                Cow::Owned(DeterministicFiniteAutomaton::from_nfa(lazy.nfa().clone()))
+            }
+        }
+    }
+
+    fn matches(&self, chars: impl IntoIterator<Item = char>) -> bool {
+        match self {
+            Engine::Dfa(dfa) => matches(dfa, chars),
+            Engine::Nfa(nfa) => matches(nfa, chars),
+            Engine::Lazy(lazy) => matches(lazy, chars),
+        }
+    }
+
+    fn run(&self, text: &str) -> RunResult {
+        match self {
+            Engine::Dfa(dfa) => run(dfa, text),
+            Engine::Nfa(nfa) => run(nfa, text),
+            Engine::Lazy(lazy) => run(lazy, text),
+        }
+    }
+
+    fn longest_match_at(&self, text: &str, start: usize) -> Option<usize> {
+        match self {
+            Engine::Dfa(dfa) => longest_match_at(dfa, text, start),
+            Engine::Nfa(nfa) => longest_match_at(nfa, text, start),
+            Engine::Lazy(lazy) => longest_match_at(lazy, text, start),
+        }
+    }
+
+    fn first_match_end(&self, chars: impl IntoIterator<Item = char>) -> Option<usize> {
+        match self {
+            Engine::Dfa(dfa) => first_match_end(dfa, chars),
+            Engine::Nfa(nfa) => first_match_end(nfa, chars),
+            Engine::Lazy(lazy) => first_match_end(lazy, chars),
+        }
+    }
+
+    fn match_ends(&self, text: &str, start: usize, restart: bool) -> Vec<usize> {
+        match self {
+            Engine::Dfa(dfa) => match_ends(dfa, text, start, restart),
+            Engine::Nfa(nfa) => match_ends(nfa, text, start, restart),
+            Engine::Lazy(lazy) => match_ends(lazy, text, start, restart),
+        }
+    }
+
+    fn shortest_match_at(&self, text: &str, start: usize) -> Option<usize> {
+        match self {
+            Engine::Dfa(dfa) => shortest_match_at(dfa, text, start),
+            Engine::Nfa(nfa) => shortest_match_at(nfa, text, start),
+            Engine::Lazy(lazy) => shortest_match_at(lazy, text, start),
+        }
+    }
+}
+
+fn matches(automaton: &impl Automaton, chars: impl IntoIterator<Item = char>) -> bool {
+    let mut current_state = automaton.start_state();
+    for char in chars {
+        if let Some(state) = automaton.step(&current_state, char) {
+            current_state = state;
+        } else {
+            return false;
+        }
+    }
+    automaton.is_accept(&current_state)
+}
+
+fn run(automaton: &impl Automaton, text: &str) -> RunResult {
+    let mut current_state = automaton.start_state();
+    let mut consumed = 0;
+    for char in text.chars() {
+        match automaton.step(&current_state, char) {
+            Some(state) => current_state = state,
+            None => {
+                return RunResult {
+                    accepted: false,
+                    stuck: true,
+                    consumed,
+                }
+            }
+        }
+        consumed += 1;
+    }
+    RunResult {
+        accepted: automaton.is_accept(&current_state),
+        stuck: false,
+        consumed,
+    }
+}
+
+fn matches_with_budget(
+    automaton: &impl Automaton,
+    text: &str,
+    max_steps: usize,
+) -> Result<bool, BudgetExceeded> {
+    let mut current_state = automaton.start_state();
+    for (steps, char) in text.chars().enumerate() {
+        if steps == max_steps {
+            return Err(BudgetExceeded { max_steps });
+        }
+        match automaton.step(&current_state, char) {
+            Some(state) => current_state = state,
+            None => return Ok(false),
+        }
+    }
+    Ok(automaton.is_accept(&current_state))
+}
+
+/// Runs the automaton from every position at once, tracking the distinct states reached so far,
+/// so that the suffixes sharing a state are not run twice.
+fn matches_suffix<A: Automaton>(automaton: &A, text: &str) -> bool
+where
+    A::State: PartialEq,
+{
+    let mut states = vec![automaton.start_state()];
+    for char in text.chars() {
+        let mut next_states = vec![automaton.start_state()];
+        for state in states
+            .iter()
+            .filter_map(|state| automaton.step(state, char))
+        {
+            if !next_states.contains(&state) {
+                next_states.push(state);
+            }
+        }
+        states = next_states;
+    }
+    states.iter().any(|state| automaton.is_accept(state))
+}
+
+/// Like `matches_suffix`, but returns as soon as a state reached from some position accepts, with
+/// the number of characters consumed until then. No character after that is consumed.
+fn first_match_end<A: Automaton>(
+    automaton: &A,
+    chars: impl IntoIterator<Item = char>,
+) -> Option<usize>
+where
+    A::State: PartialEq,
+{
+    let mut states = vec![automaton.start_state()];
+    if automaton.is_accept(&states[0]) {
+        return Some(0);
+    }
+    for (i, char) in chars.into_iter().enumerate() {
+        let mut next_states = vec![automaton.start_state()];
+        for state in states
+            .iter()
+            .filter_map(|state| automaton.step(state, char))
+        {
+            if automaton.is_accept(&state) {
+                return Some(i + 1);
+            }
+            if !next_states.contains(&state) {
+                next_states.push(state);
+            }
+        }
+        states = next_states;
+    }
+    None
+}
+
+/// Returns true if byte offset `i` of `text` is not between two word characters, which are the
+/// characters of `\w`. A whole-word match must start and end at such offsets.
+fn is_word_boundary(text: &str, i: usize) -> bool {
+    !text[..i].chars().next_back().is_some_and(is_word_char)
+        || !text[i..].chars().next().is_some_and(is_word_char)
+}
+
+fn is_word_char(char: char) -> bool {
+    char.is_ascii_alphanumeric() || char == '_'
+}
+
+/// Returns the ends of the matches starting at byte offset `start`, or at any later offset too if
+/// `restart`.
+fn match_ends<A: Automaton>(automaton: &A, text: &str, start: usize, restart: bool) -> Vec<usize>
+where
+    A::State: PartialEq,
+{
+    let mut states = vec![automaton.start_state()];
+    let mut ret = vec![];
+    if automaton.is_accept(&states[0]) {
+        ret.push(start);
+    }
+    for (i, char) in text[start..].char_indices() {
+        let mut next_states = match restart {
+            true => vec![automaton.start_state()],
+            false => vec![],
+        };
+        for state in states
+            .iter()
+            .filter_map(|state| automaton.step(state, char))
+        {
+            if !next_states.contains(&state) {
+                next_states.push(state);
+            }
+        }
+        if next_states.is_empty() {
+            break;
+        }
+        if next_states.iter().any(|state| automaton.is_accept(state)) {
+            ret.push(start + i + char.len_utf8());
+        }
+        states = next_states;
+    }
+    ret
+}
+
+fn longest_match_at(automaton: &impl Automaton, text: &str, start: usize) -> Option<usize> {
+    let mut current_state = automaton.start_state();
+    let mut ret = automaton.is_accept(&current_state).then_some(start);
+    for (i, char) in text[start..].char_indices() {
+        match automaton.step(&current_state, char) {
+            Some(state) => current_state = state,
+            None => break,
+        }
+        if automaton.is_accept(&current_state) {
+            ret = Some(start + i + char.len_utf8());
+        }
+    }
+    ret
+}
+
+fn shortest_match_at(automaton: &impl Automaton, text: &str, start: usize) -> Option<usize> {
+    let mut current_state = automaton.start_state();
+    if automaton.is_accept(&current_state) {
+        return Some(start);
+    }
+    for (i, char) in text[start..].char_indices() {
+        current_state = automaton.step(&current_state, char)?;
+        if automaton.is_accept(&current_state) {
+            return Some(start + i + char.len_utf8());
+        }
+    }
+    None
+}
+
+/// Shows the size of the automaton the regex is matched with, without its transitions, e.g.
+/// `Regex { backend: Dfa, states: 3, start: 0, accepts: [2], alphabet: "ab" }`.
+impl core::fmt::Debug for Regex {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        fn sorted(states: impl Iterator<Item = u32>) -> Vec<u32> {
+            let mut ret = states.collect::<Vec<_>>();
+            ret.sort();
+            ret
+        }
+        let (backend, nfa) = match &self.engine {
+            Engine::Dfa(dfa) => {
+                return f
+                    .debug_struct("Regex")
+                    .field("backend", &Backend::Dfa)
+                    .field("states", &dfa.state_count())
+                    .field("start", &dfa.start().0)
+                    .field("accepts", &sorted(dfa.accepts().iter().map(|s| s.0)))
+                    .field("alphabet", &dfa.alphabet().into_iter().collect::<String>())
+                    .finish();
             }
+            Engine::Nfa(nfa) => (Backend::Nfa, nfa),
+            Engine::Lazy(lazy) => (Backend::LazyDfa, lazy.nfa()),
+        };
+        f.debug_struct("Regex")
+            .field("backend", &backend)
+            .field("states", &nfa.state_bound())
+            .field("start", &nfa.start().0)
+            .field("accepts", &sorted(nfa.accepts().iter().map(|s| s.0)))
+            .finish()
+    }
+}
+
+/// Two regexes are equal when they match exactly the same strings, see [`Regex::equivalent`].
+///
+/// The comparison is semantic, not structural: `(a|b)` equals `[ba]`. It builds the product of
+/// both automata, so it can be expensive for large regexes.
+impl PartialEq for Regex {
+    fn eq(&self, other: &Regex) -> bool {
+        self.equivalent(other)
+    }
+}
+
+impl Eq for Regex {}
+
+impl core::str::FromStr for Regex {
+    type Err = RegexError;
+
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        Regex::new(pattern)
+    }
+}
+
+/// Maps the characters of a text before they are matched, see [`Regex::matches_with_mapper`].
+pub trait CharMapper {
+    fn map(&self, char: char) -> char;
+}
+
+impl<F: Fn(char) -> char> CharMapper for F {
+    fn map(&self, char: char) -> char {
+        self(char)
+    }
+}
+
+/// The size of a compiled DFA, see [`Regex::stats`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RegexStats {
+    pub num_states: usize,
+    /// The transitions by a character of their own plus the default transitions, which are taken
+    /// by every other character.
+    pub num_transitions: usize,
+    pub num_accept_states: usize,
+    /// The number of characters that have a transition of their own, see [`Regex::alphabet`].
+    pub alphabet_size: usize,
+    /// True if no DFA with fewer states matches the same strings.
+    pub is_minimal: bool,
+}
+
+/// How a run of the automaton over a text ended, see [`Regex::run`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RunResult {
+    /// True if the run read the whole text and ended in an accept state.
+    pub accepted: bool,
+    /// True if the run stopped before the end of the text, as no string starting with the
+    /// characters read so far plus the next one can match.
+    pub stuck: bool,
+    /// The number of characters read before the run ended.
+    pub consumed: usize,
+}
+
+pub struct FindIter<'r, 't> {
+    regex: &'r Regex,
+    text: Normalized<'t>,
+    /// The byte offset in the normalized text to search from.
+    position: usize,
+    /// The end of the last match in the original text.
+    last_end: usize,
+}
+
+impl Iterator for FindIter<'_, '_> {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let text = &*self.text.text;
+        if self.position > text.len() {
+            return None;
         }
-        self.dfa.accepts.contains(&current_state)
+        let Some((start, end)) = self.regex.find_at(text, self.position) else {
+            self.position = text.len() + 1;
+            return None;
+        };
+        // Advance by one character after a zero-width match to avoid an infinite loop.
+        self.position = match start == end {
+            true => end + text[end..].chars().next().map_or(1, |c| c.len_utf8()),
+            false => end,
+        };
+        // A match starting in the chunk where the last one ends starts after it instead.
+        let (start, end) = self.text.range_in_original((start, end));
+        let start = start.max(self.last_end);
+        self.last_end = end;
+        Some((start, end))
     }
 }
 
@@ -38,9 +1282,96 @@ mod tests {
 
     #[test]
     fn syntax_error() {
-        for test in [r"ab(cd", r"e(*)f", r")h", r"i|*", r"*", r"+", r"a*+"] {
+        for test in [
+            r"ab(cd", r"e(*)f", r")h", r"i|*", r"*", r"+", r"a*+", r"abc\", r"[a\",
+        ] {
             let regex = Regex::new(test);
             assert!(regex.is_err());
         }
     }
+
+    #[test]
+    fn syntax_error_kind() {
+        assert!(matches!(
+            Regex::new(r"a("),
+            Err(RegexError::UnbalancedParen { .. })
+        ));
+        assert!(matches!(
+            Regex::new(r"a\"),
+            Err(RegexError::TrailingEscape { .. })
+        ));
+        assert!(matches!(
+            Regex::new(r"a[]"),
+            Err(RegexError::EmptyClass { .. })
+        ));
+        assert_eq!(
+            Regex::new(r"a|*").err().unwrap().to_string(),
+            "Expected one of ['EOF'], found '*' at byte 2"
+        );
+        assert_eq!(
+            Regex::new(r"a(?i)b").err().unwrap(),
+            RegexError::UnsupportedGroup {
+                group: "(?i".to_string(),
+                span: 1
+            }
+        );
+        assert_eq!(
+            Regex::new(r"a(?=b)").err().unwrap().to_string(),
+            "Unsupported pattern, lookaround is not regular at byte 1"
+        );
+        assert_eq!(
+            Regex::new(r"(a)\1").err().unwrap().to_string(),
+            "Unsupported pattern, backreferences are not regular at byte 3"
+        );
+        for pattern in [
+            r"(?<!a)b",
+            r"(?<name>a)",
+            r"(?P<name>a)",
+            r"(?'name'a)",
+            r"[\1]",
+        ] {
+            assert!(
+                matches!(Regex::new(pattern), Err(RegexError::Unsupported { .. })),
+                "{pattern}"
+            );
+        }
+    }
+
+    #[test]
+    fn syntax_error_span() {
+        let error = Regex::new(r"ab)c").err().unwrap();
+        assert_eq!(error, RegexError::UnbalancedParen { span: 2 });
+        assert_eq!(error.to_string(), "Unbalanced parenthesis at byte 2");
+        assert_eq!(Regex::new(r"山田\xZZ").err().unwrap().span(), 6);
+        assert_eq!(Regex::new(r"a{3,1}").err().unwrap().span(), 2);
+    }
+
+    #[test]
+    fn state_count() {
+        // The start state and one state for each of 'a', 'b' and 'c'.
+        assert_eq!(Regex::new(r"(a|b|c)").unwrap().state_count(), 4);
+        assert_eq!(Regex::new(r"a*").unwrap().state_count(), 2);
+        assert_eq!(Regex::new(r"").unwrap().state_count(), 1);
+        // The dead state is kept for the digits, which must not fall back to the default transition.
+        assert_eq!(Regex::new(r"\D").unwrap().state_count(), 3);
+    }
+
+    #[test]
+    fn state_count_pruned() {
+        // The pair of states after 'b' can never reach an accept state.
+        let regex = Regex::new(r"a|bc").unwrap();
+        let regex = regex.intersect(&Regex::new(r"a|bd").unwrap());
+        assert_eq!(regex.state_count(), 2);
+        assert!(regex.matches("a"));
+        assert!(!regex.matches("b"));
+        assert!(!regex.matches("bc"));
+    }
+
+    #[test]
+    fn to_dot() {
+        let dot = Regex::new(r"山|a").unwrap().to_dot();
+        assert!(dot.starts_with("digraph"));
+        assert!(dot.contains("doublecircle"));
+        assert!(dot.contains(r#"[label = "山"]"#));
+    }
 }
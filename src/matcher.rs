@@ -0,0 +1,81 @@
+use crate::{EngineState, Regex};
+
+/// Matches a text that is fed character by character, e.g. as it arrives from a stream.
+pub struct Matcher<'r> {
+    regex: &'r Regex,
+    /// `None` once no string starting with the fed characters can match.
+    state: Option<EngineState>,
+}
+
+impl<'r> Matcher<'r> {
+    pub(crate) fn new(regex: &'r Regex) -> Matcher<'r> {
+        Matcher {
+            regex,
+            state: Some(regex.engine.start_state()),
+        }
+    }
+
+    /// Feeds the next character. Returns false if the fed characters can no longer be extended
+    /// to a match, in which case later characters are ignored until `reset`. The character is not
+    /// normalized, even if the regex is built with `RegexBuilder::normalize`.
+    pub fn feed(&mut self, char: char) -> bool {
+        let engine = &self.regex.engine;
+        self.state = self
+            .state
+            .take()
+            .and_then(|state| engine.step(&state, char));
+        self.state.is_some()
+    }
+
+    /// Feeds every character of `text`, which is normalized on its own, apart from the characters
+    /// fed before. Returns the same as `feed` for the last character.
+    pub fn feed_str(&mut self, text: &str) -> bool {
+        let regex = self.regex;
+        regex
+            .normalized(text)
+            .text
+            .chars()
+            .all(|char| self.feed(char))
+    }
+
+    /// Returns true if the regex matches the characters fed so far.
+    pub fn is_accepting(&self) -> bool {
+        self.state
+            .as_ref()
+            .is_some_and(|state| self.regex.engine.is_accept(state))
+    }
+
+    /// Forgets the characters fed so far.
+    pub fn reset(&mut self) {
+        *self = Matcher::new(self.regex);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn feed() {
+        for regex in [
+            Regex::new(r"abc"),
+            Regex::new_nfa(r"abc"),
+            Regex::new_lazy(r"abc"),
+        ] {
+            let regex = regex.unwrap();
+            let mut matcher = regex.matcher();
+            assert!(matcher.feed_str("ab"));
+            assert!(!matcher.is_accepting());
+            assert!(matcher.feed('c'));
+            assert!(matcher.is_accepting());
+            assert!(!matcher.feed('c'));
+            assert!(!matcher.is_accepting());
+            assert!(!matcher.feed('a'));
+
+            matcher.reset();
+            assert!(!matcher.is_accepting());
+            assert!(matcher.feed('a'));
+            assert!(!matcher.feed_str("bd"));
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use crate::automaton::*;
+use crate::prelude::*;
+use crate::{parse, RegexError};
+use alloc::collections::BTreeSet;
+
+/// Several patterns compiled into one automaton, which tells which of them matched.
+pub struct MultiRegex<L> {
+    dfa: DeterministicFiniteAutomaton,
+    /// tags[state] = The indices of the patterns that match when `state` is reached.
+    tags: HashMap<DFAState, BTreeSet<usize>>,
+    labels: Vec<L>,
+}
+
+impl<L: Clone> MultiRegex<L> {
+    /// As the patterns match prefixes of the text, `^` matches at the start of the match and `$`
+    /// never matches.
+    pub fn new(patterns: &[(&str, L)]) -> Result<MultiRegex<L>, RegexError> {
+        let nfas = patterns
+            .iter()
+            .map(|(pattern, _)| {
+                let nfa = NondeterministicFiniteAutomaton::from_node_with_options(
+                    parse(pattern)?,
+                    false,
+                    false,
+                );
+                Ok(nfa.resolve_anchors(true, false))
+            })
+            .collect::<Result<Vec<_>, RegexError>>()?;
+        let (nfa, accepts) = NondeterministicFiniteAutomaton::union_all(&nfas);
+        let tags = accepts
+            .into_iter()
+            .enumerate()
+            .flat_map(|(i, accepts)| accepts.into_iter().map(move |accept| (accept, i)))
+            .collect();
+        let (dfa, tags) = DeterministicFiniteAutomaton::from_nfa_with_tags(nfa, &tags);
+        Ok(MultiRegex {
+            dfa,
+            tags,
+            labels: patterns.iter().map(|(_, label)| label.clone()).collect(),
+        })
+    }
+
+    /// Returns the byte length of the longest prefix of `text` that some pattern matches, and the
+    /// label of that pattern. If several patterns match it, the one declared first wins.
+    pub fn match_longest(&self, text: &str) -> Option<(usize, L)> {
+        let mut current_state = self.dfa.start_state();
+        let mut ret = self.first_tag(current_state).map(|tag| (0, tag));
+        for (i, char) in text.char_indices() {
+            match self.dfa.step(&current_state, char) {
+                Some(state) => current_state = state,
+                None => break,
+            }
+            if let Some(tag) = self.first_tag(current_state) {
+                ret = Some((i + char.len_utf8(), tag));
+            }
+        }
+        ret.map(|(len, tag)| (len, self.labels[tag].clone()))
+    }
+
+    fn first_tag(&self, state: DFAState) -> Option<usize> {
+        self.tags.get(&state).and_then(|tags| tags.first()).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Token {
+        If,
+        Identifier,
+        Number,
+    }
+
+    #[test]
+    fn match_longest() {
+        let regex = MultiRegex::new(&[
+            (r"if", Token::If),
+            (r"\w+", Token::Identifier),
+            (r"\d+", Token::Number),
+        ])
+        .unwrap();
+        assert_eq!(regex.match_longest("if x"), Some((2, Token::If)));
+        assert_eq!(regex.match_longest("iffy"), Some((4, Token::Identifier)));
+        assert_eq!(regex.match_longest("i"), Some((1, Token::Identifier)));
+        // Both patterns match "42", and the identifier is declared first.
+        assert_eq!(regex.match_longest("42"), Some((2, Token::Identifier)));
+        assert_eq!(regex.match_longest(" if"), None);
+        assert!(MultiRegex::new(&[(r"a(", Token::If)]).is_err());
+    }
+}
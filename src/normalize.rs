@@ -0,0 +1,220 @@
+//! The texts given to a regex built with `RegexBuilder::normalize`, converted to its
+//! normalization form, with the offsets in them mapped back to the texts as given.
+
+use crate::prelude::*;
+use alloc::borrow::Cow;
+
+/// A text converted to the normalization form of a regex. It is split into chunks that are
+/// normalized independently, so an offset between two chunks maps to the same place in the
+/// original text, while one inside a chunk maps to an end of it.
+pub(crate) struct Normalized<'t> {
+    pub(crate) text: Cow<'t, str>,
+    /// The byte offsets in `text` and in the original text where each chunk ends, in ascending
+    /// order. Empty if `text` is the original.
+    ends: Vec<(usize, usize)>,
+}
+
+impl<'t> Normalized<'t> {
+    pub(crate) fn original(text: &'t str) -> Normalized<'t> {
+        Normalized {
+            text: Cow::Borrowed(text),
+            ends: vec![],
+        }
+    }
+
+    #[cfg(feature = "unicode-normalization")]
+    pub(crate) fn nfc(text: &'t str) -> Normalized<'t> {
+        use unicode_normalization::{is_nfc_quick, IsNormalized};
+        if is_nfc_quick(text.chars()) == IsNormalized::Yes {
+            return Normalized::original(text);
+        }
+        let mut normalized = String::with_capacity(text.len());
+        let mut ends = vec![];
+        let mut original_len = 0;
+        for chunk in NfcChunks::new(text.chars()) {
+            normalized.extend(chunk.normalized);
+            original_len += chunk.original_len;
+            ends.push((normalized.len(), original_len));
+        }
+        Normalized {
+            text: Cow::Owned(normalized),
+            ends,
+        }
+    }
+
+    /// Returns true if `text` is the original text, so that the offsets need no mapping.
+    pub(crate) fn is_original(&self) -> bool {
+        self.ends.is_empty()
+    }
+
+    /// Maps the start of a match at byte offset `start` of `text` to the original text, moving it
+    /// back to the start of its chunk.
+    pub(crate) fn start_in_original(&self, start: usize) -> usize {
+        if self.is_original() {
+            return start;
+        }
+        match self.ends.partition_point(|(end, _)| *end <= start) {
+            0 => 0,
+            i => self.ends[i - 1].1,
+        }
+    }
+
+    /// Maps the end of a match at byte offset `end` of `text` to the original text, moving it
+    /// forward to the end of its chunk.
+    pub(crate) fn end_in_original(&self, end: usize) -> usize {
+        if self.is_original() || end == 0 {
+            return end;
+        }
+        let i = self.ends.partition_point(|(chunk_end, _)| *chunk_end < end);
+        self.ends[i].1
+    }
+
+    pub(crate) fn range_in_original(&self, (start, end): (usize, usize)) -> (usize, usize) {
+        (self.start_in_original(start), self.end_in_original(end))
+    }
+
+    /// Maps byte offset `offset` of the original text to `text`, or returns `None` if it is inside
+    /// a chunk, where no normalized character starts.
+    pub(crate) fn offset_in_text(&self, offset: usize) -> Option<usize> {
+        if self.is_original() || offset == 0 {
+            return Some(offset);
+        }
+        let i = self.ends.partition_point(|(_, end)| *end < offset);
+        self.ends
+            .get(i)
+            .filter(|(_, end)| *end == offset)
+            .map(|(end, _)| *end)
+    }
+}
+
+/// The characters read from a stream, converted to the normalization form of a regex.
+pub(crate) enum NormalizedChars<I: Iterator<Item = char>> {
+    Original {
+        chars: I,
+        read: usize,
+    },
+    #[cfg(feature = "unicode-normalization")]
+    Nfc {
+        chunks: NfcChunks<I>,
+        /// The rest of the last normalized chunk.
+        chunk: alloc::vec::IntoIter<char>,
+        read: usize,
+    },
+}
+
+impl<I: Iterator<Item = char>> NormalizedChars<I> {
+    pub(crate) fn original(chars: I) -> NormalizedChars<I> {
+        NormalizedChars::Original { chars, read: 0 }
+    }
+
+    #[cfg(feature = "unicode-normalization")]
+    pub(crate) fn nfc(chars: I) -> NormalizedChars<I> {
+        NormalizedChars::Nfc {
+            chunks: NfcChunks::new(chars),
+            chunk: vec![].into_iter(),
+            read: 0,
+        }
+    }
+
+    /// Returns the number of the original characters the ones returned so far are normalized
+    /// from, which includes the rest of the chunk of the last one.
+    pub(crate) fn read(&self) -> usize {
+        match self {
+            NormalizedChars::Original { read, .. } => *read,
+            #[cfg(feature = "unicode-normalization")]
+            NormalizedChars::Nfc { read, .. } => *read,
+        }
+    }
+}
+
+impl<I: Iterator<Item = char>> Iterator for NormalizedChars<I> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            NormalizedChars::Original { chars, read } => {
+                let char = chars.next()?;
+                *read += 1;
+                Some(char)
+            }
+            #[cfg(feature = "unicode-normalization")]
+            NormalizedChars::Nfc {
+                chunks,
+                chunk,
+                read,
+            } => loop {
+                if let Some(char) = chunk.next() {
+                    return Some(char);
+                }
+                let next = chunks.next()?;
+                *read += next.original_count;
+                *chunk = next.normalized.into_iter();
+            },
+        }
+    }
+}
+
+/// Splits a text into chunks whose NFC forms concatenate to the NFC form of the text. A chunk
+/// ends before a character that neither is a combining mark nor composes with the chunk, so the
+/// character after a chunk is read before it is returned.
+#[cfg(feature = "unicode-normalization")]
+pub(crate) struct NfcChunks<I: Iterator<Item = char>> {
+    chars: core::iter::Peekable<I>,
+}
+
+#[cfg(feature = "unicode-normalization")]
+pub(crate) struct NfcChunk {
+    normalized: Vec<char>,
+    /// The number of characters and the byte length of the chunk in the original text.
+    original_count: usize,
+    original_len: usize,
+}
+
+#[cfg(feature = "unicode-normalization")]
+impl<I: Iterator<Item = char>> NfcChunks<I> {
+    fn new(chars: I) -> NfcChunks<I> {
+        NfcChunks {
+            chars: chars.peekable(),
+        }
+    }
+}
+
+#[cfg(feature = "unicode-normalization")]
+impl<I: Iterator<Item = char>> Iterator for NfcChunks<I> {
+    type Item = NfcChunk;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        use unicode_normalization::char::{
+            canonical_combining_class, compose, decompose_canonical,
+        };
+        use unicode_normalization::UnicodeNormalization;
+
+        let mut original = String::from(self.chars.next()?);
+        let mut original_count = 1;
+        let mut normalized = None;
+        while let Some(&char) = self.chars.peek() {
+            if canonical_combining_class(char) == 0 {
+                let last = *normalized
+                    .get_or_insert_with(|| original.nfc().collect::<Vec<_>>())
+                    .last()
+                    .unwrap();
+                let mut first = None;
+                decompose_canonical(char, |char| {
+                    first.get_or_insert(char);
+                });
+                if first.and_then(|first| compose(last, first)).is_none() {
+                    break;
+                }
+            }
+            original.push(char);
+            original_count += 1;
+            normalized = None;
+            self.chars.next();
+        }
+        Some(NfcChunk {
+            normalized: normalized.unwrap_or_else(|| original.nfc().collect()),
+            original_count,
+            original_len: original.len(),
+        })
+    }
+}
@@ -1,38 +1,385 @@
+use crate::error::RegexError;
 use crate::lexer::*;
+use crate::prelude::*;
+use alloc::collections::BTreeSet;
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+const STACKED_QUANTIFIER: &str = "quantifiers can not be stacked, use a group like '(a*)?' instead";
+
+/// The largest count allowed in `{n,m}`.
+const MAX_REPETITION: u32 = 1000;
+
+/// The largest number of nodes a `{n,m}` is allowed to expand to, counting every copy.
+const MAX_REPETITION_NODES: usize = 10_000;
+
+/// The AST of a pattern.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Node {
     Character(char),
+    AnyChar,
+    Class(BTreeSet<char>),
+    NegatedClass(BTreeSet<char>),
     Empty,
+    /// `^`, which matches the empty string at the start of the text.
+    StartAnchor,
+    /// `$`, which matches the empty string at the end of the text.
+    EndAnchor,
     Star(Box<Node>),
     Union(Box<Node>, Box<Node>),
     Concat(Box<Node>, Box<Node>),
 }
 
-fn error_msg(expected: &[Token], actual: Token) -> String {
-    let expected = expected
-        .iter()
-        .map(|token| format!("'{}'", token))
-        .collect::<Vec<_>>()
-        .join(", ");
-    let actual = match actual {
-        Token::Character(char) => format!("'{}'", char),
-        _ => format!("'{}'", actual),
+/// Prints a pattern that parses into an equivalent AST. Parentheses are inserted only where the
+/// precedence requires them. A `NegatedClass` other than `\D`, `\W` and `\S` has no syntax, so it
+/// is printed as `[^...]`, which the parser rejects as an unsupported negated class instead of
+/// reading it as a different class.
+impl core::fmt::Display for Node {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.write(f, Precedence::Union)
+    }
+}
+
+/// The context a node is printed in, from the loosest to the tightest binding.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Precedence {
+    Union,
+    Concat,
+    Repetition,
+}
+
+impl Node {
+    fn write(&self, f: &mut core::fmt::Formatter<'_>, precedence: Precedence) -> core::fmt::Result {
+        let precedence_of_self = match self {
+            node if node.is_repetition() => Precedence::Repetition,
+            Node::Union(_, _) => Precedence::Union,
+            Node::Concat(_, _) => Precedence::Concat,
+            _ => Precedence::Repetition,
+        };
+        // `()` for the empty string, and e.g. `(a*)*` because `a**` does not parse.
+        let parenthesize = precedence_of_self < precedence
+            || (precedence == Precedence::Repetition
+                && (self.is_repetition() || *self == Node::Empty));
+        if parenthesize {
+            write!(f, "(")?;
+        }
+        match self {
+            Node::Character(char) => write_char(f, *char, METACHARACTERS)?,
+            Node::AnyChar => write!(f, ".")?,
+            Node::Class(chars) => write_class(f, chars, false)?,
+            Node::NegatedClass(chars) => write_class(f, chars, true)?,
+            Node::Empty => {}
+            Node::StartAnchor => write!(f, "^")?,
+            Node::EndAnchor => write!(f, "$")?,
+            Node::Star(node) => {
+                node.write(f, Precedence::Repetition)?;
+                write!(f, "*")?;
+            }
+            // Loops over the right operands instead of recursing, as the parser nests them there.
+            Node::Union(_, _) | Node::Concat(_, _) => {
+                let mut node = self;
+                loop {
+                    match node {
+                        Node::Union(node1, node2) if **node2 == Node::Empty => {
+                            node1.write(f, Precedence::Repetition)?;
+                            write!(f, "?")?;
+                            break;
+                        }
+                        Node::Concat(node1, node2) if is_plus(node1, node2) => {
+                            node1.write(f, Precedence::Repetition)?;
+                            write!(f, "+")?;
+                            break;
+                        }
+                        Node::Union(node1, node2) if precedence_of_self == Precedence::Union => {
+                            node1.write(f, Precedence::Union)?;
+                            write!(f, "|")?;
+                            node = node2;
+                        }
+                        Node::Concat(node1, node2) if precedence_of_self == Precedence::Concat => {
+                            node1.write(f, Precedence::Concat)?;
+                            node = node2;
+                        }
+                        node => {
+                            node.write(f, precedence_of_self)?;
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        if parenthesize {
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+
+    /// Returns true if this node is printed with a trailing `*`, `+` or `?`.
+    fn is_repetition(&self) -> bool {
+        match self {
+            Node::Star(_) => true,
+            Node::Union(_, node) => **node == Node::Empty,
+            Node::Concat(node1, node2) => is_plus(node1, node2),
+            _ => false,
+        }
+    }
+}
+
+/// Returns true if `Concat(node1, node2)` is `X+`, which the parser expands to `XX*`.
+fn is_plus(node1: &Node, node2: &Node) -> bool {
+    matches!(node2, Node::Star(node) if **node == *node1)
+}
+
+fn is_quantifier(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::StarOperator | Token::PlusOperator | Token::QuestionOperator | Token::LeftBrace
+    )
+}
+
+fn write_char(f: &mut core::fmt::Formatter<'_>, char: char, special: &[char]) -> core::fmt::Result {
+    match char {
+        '\n' => write!(f, "\\n"),
+        '\t' => write!(f, "\\t"),
+        '\r' => write!(f, "\\r"),
+        '\0' => write!(f, "\\0"),
+        char if char.is_control() => write!(f, "\\u{{{:X}}}", char as u32),
+        char if special.contains(&char) => write!(f, "\\{}", char),
+        char => write!(f, "{}", char),
+    }
+}
+
+fn write_class(
+    f: &mut core::fmt::Formatter<'_>,
+    chars: &BTreeSet<char>,
+    negated: bool,
+) -> core::fmt::Result {
+    for escape in ['d', 'w', 's'] {
+        if *chars == class_escape_chars(escape) {
+            return match negated {
+                true => write!(f, "\\{}", escape.to_ascii_uppercase()),
+                false => write!(f, "\\{}", escape),
+            };
+        }
+    }
+    write!(f, "{}", if negated { "[^" } else { "[" })?;
+    // Prints the runs of three or more consecutive characters as ranges.
+    let mut chars = chars.iter().cloned().peekable();
+    while let Some(first) = chars.next() {
+        let mut last = first;
+        while chars
+            .peek()
+            .is_some_and(|c| Some(*c) == char::from_u32(last as u32 + 1))
+        {
+            last = chars.next().unwrap();
+        }
+        let special = &['\\', ']', '-', '^'];
+        write_char(f, first, special)?;
+        if last as u32 >= first as u32 + 2 {
+            write!(f, "-")?;
+        } else if last == first {
+            continue;
+        }
+        write_char(f, last, special)?;
+    }
+    write!(f, "]")
+}
+
+fn unexpected_token(expected: &[Token], actual: Token, span: usize) -> RegexError {
+    match (expected, actual) {
+        ([Token::RightParen], Token::EndOfFile) | ([Token::EndOfFile], Token::RightParen) => {
+            return RegexError::UnbalancedParen { span };
+        }
+        ([Token::Character(_)], Token::RightBracket) => return RegexError::EmptyClass { span },
+        _ => {}
+    }
+    let expected = expected.iter().map(|token| token.to_string()).collect();
+    let found = match actual {
+        Token::Character(char) => char.to_string(),
+        Token::Digit(digit) => digit.to_string(),
+        Token::ClassEscape(char) => format!("\\{}", char),
+        _ => actual.to_string(),
     };
-    format!("Expected one of [{}], found {}", expected, actual)
+    RegexError::UnexpectedToken {
+        expected,
+        found,
+        span,
+    }
 }
 
-type Result<T> = std::result::Result<T, String>;
+/// Returns the set of characters of `\d`, `\w` or `\s` (case-insensitive).
+fn class_escape_chars(char: char) -> BTreeSet<char> {
+    match char.to_ascii_lowercase() {
+        'd' => ('0'..='9').collect(),
+        'w' => ('a'..='z')
+            .chain('A'..='Z')
+            .chain('0'..='9')
+            .chain(['_'])
+            .collect(),
+        's' => [' ', '\t', '\n', '\r', '\x0B', '\x0C'].into(),
+        _ => unreachable!("unknown class escape '\\{}'", char),
+    }
+}
 
-pub struct Parser<'a> {
-    lexer: Lexer<'a>,
+type Result<T> = core::result::Result<T, RegexError>;
+
+pub struct Parser<I: Iterator<Item = char>> {
+    lexer: Lexer<I>,
     look: Token,
 }
 
-impl Parser<'_> {
-    pub fn new(mut lexer: Lexer) -> Parser {
-        let node = lexer.scan();
-        Parser { lexer, look: node }
+/// Drops `node` without recursion, so that a deep tree does not overflow the stack.
+pub(crate) fn drop_tree(node: Node) {
+    let mut stack = vec![node];
+    while let Some(mut node) = stack.pop() {
+        match &mut node {
+            Node::Star(child) => stack.push(core::mem::replace(&mut **child, Node::Empty)),
+            Node::Union(child1, child2) | Node::Concat(child1, child2) => {
+                stack.push(core::mem::replace(&mut **child1, Node::Empty));
+                stack.push(core::mem::replace(&mut **child2, Node::Empty));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A node the parser holds while parsing the rest, which is dropped with `drop_tree` if parsing
+/// fails before it is returned.
+struct Tree(Node);
+
+impl Tree {
+    fn into_node(mut self) -> Node {
+        core::mem::replace(&mut self.0, Node::Empty)
+    }
+}
+
+impl Drop for Tree {
+    fn drop(&mut self) {
+        drop_tree(core::mem::replace(&mut self.0, Node::Empty));
+    }
+}
+
+impl Node {
+    /// Rewrites the node into a canonical form matching the same strings. `X**` becomes `X*`,
+    /// `Empty` is dropped from concatenations, which are nested to the right, and the
+    /// alternatives of a union are flattened, sorted and deduplicated.
+    ///
+    /// Two nodes with the same normal form are equivalent, but equivalent nodes may have
+    /// different normal forms, e.g. `a*` and `(a|)*`.
+    pub fn normalize(self) -> Node {
+        match self {
+            Node::Star(_) => {
+                let mut node = self;
+                let Node::Star(child) = &mut node else {
+                    unreachable!()
+                };
+                match core::mem::replace(&mut **child, Node::Empty).normalize() {
+                    child @ Node::Star(_) => child,
+                    child => Node::Star(Box::new(child)),
+                }
+            }
+            Node::Union(_, _) => {
+                let mut alternatives = self
+                    .operands()
+                    .into_iter()
+                    .map(Node::normalize)
+                    .flat_map(|node| match node {
+                        Node::Union(_, _) => node.operands(),
+                        node => vec![node],
+                    })
+                    .collect::<Vec<_>>();
+                alternatives.sort();
+                alternatives.dedup();
+                nest(alternatives, Node::Union)
+            }
+            Node::Concat(_, _) => {
+                let factors = self
+                    .operands()
+                    .into_iter()
+                    .map(Node::normalize)
+                    .flat_map(|node| match node {
+                        Node::Concat(_, _) => node.operands(),
+                        Node::Empty => vec![],
+                        node => vec![node],
+                    })
+                    .collect();
+                nest(factors, Node::Concat)
+            }
+            node => node,
+        }
+    }
+
+    /// Returns true if the node matches the empty string, e.g. `a?`, `(a|b*)` and `^`.
+    pub fn is_nullable(&self) -> bool {
+        // A union with a non-nullable left operand, or a concatenation with a nullable one,
+        // depends on its right operand only, which the loop visits next.
+        let mut node = self;
+        loop {
+            match node {
+                Node::Character(_) | Node::AnyChar | Node::Class(_) | Node::NegatedClass(_) => {
+                    return false
+                }
+                Node::Empty | Node::StartAnchor | Node::EndAnchor | Node::Star(_) => return true,
+                Node::Union(node1, node2) => match node1.is_nullable() {
+                    true => return true,
+                    false => node = node2,
+                },
+                Node::Concat(node1, node2) => match node1.is_nullable() {
+                    true => node = node2,
+                    false => return false,
+                },
+            }
+        }
+    }
+
+    /// Returns the number of nodes in the tree this node is the root of.
+    fn node_count(&self) -> usize {
+        let mut ret = 0;
+        let mut stack = vec![self];
+        while let Some(node) = stack.pop() {
+            ret += 1;
+            match node {
+                Node::Star(child) => stack.push(child),
+                Node::Union(child1, child2) | Node::Concat(child1, child2) => {
+                    stack.extend([&**child1, &**child2])
+                }
+                _ => {}
+            }
+        }
+        ret
+    }
+
+    /// Returns the operands of the chain of `Union` or `Concat` this node is the root of, from
+    /// left to right.
+    fn operands(self) -> Vec<Node> {
+        let kind = core::mem::discriminant(&self);
+        let mut ret = vec![];
+        let mut stack = vec![self];
+        while let Some(mut node) = stack.pop() {
+            if core::mem::discriminant(&node) == kind {
+                if let Node::Union(node1, node2) | Node::Concat(node1, node2) = &mut node {
+                    stack.push(core::mem::replace(&mut **node2, Node::Empty));
+                    stack.push(core::mem::replace(&mut **node1, Node::Empty));
+                    continue;
+                }
+            }
+            ret.push(node);
+        }
+        ret
+    }
+}
+
+/// Nests `nodes` to the right with `operator`, as the parser does. No nodes make `Empty`.
+fn nest(nodes: Vec<Node>, operator: fn(Box<Node>, Box<Node>) -> Node) -> Node {
+    nodes
+        .into_iter()
+        .rev()
+        .reduce(|acc, node| operator(Box::new(node), Box::new(acc)))
+        .unwrap_or(Node::Empty)
+}
+
+impl<I: Iterator<Item = char>> Parser<I> {
+    pub fn new(mut lexer: Lexer<I>) -> Result<Self> {
+        let node = lexer.scan()?;
+        Ok(Parser { lexer, look: node })
     }
 
     pub fn parse(&mut self) -> Result<Node> {
@@ -42,105 +389,321 @@ impl Parser<'_> {
     fn match_next(&mut self, token: Token) -> Result<()> {
         match &self.look {
             look if *look == token => {
-                self.look = self.lexer.scan();
+                self.look = self.lexer.scan()?;
                 Ok(())
             }
-            other => Err(error_msg(&[token], *other)),
+            other => Err(unexpected_token(&[token], *other, self.lexer.token_start())),
         }
     }
 
     /// expression := sub_expression EOF
     fn expression(&mut self) -> Result<Node> {
-        let expression = self.sub_expression();
+        let expression = Tree(self.sub_expression()?);
         self.match_next(Token::EndOfFile)?;
-        expression
+        Ok(expression.into_node())
     }
 
     /// sub_expression := sequence '|' sub_expression | sequence
     fn sub_expression(&mut self) -> Result<Node> {
-        let sequence = self.sequence();
-        match &self.look {
-            Token::UnionOperator => {
-                self.match_next(Token::UnionOperator)?;
-                Ok(Node::Union(
-                    Box::new(sequence?),
-                    Box::new(self.sub_expression()?),
-                ))
-            }
-            _ => sequence,
+        // Loops instead of recursing, so that a long pattern does not overflow the stack.
+        let mut sequences = vec![Tree(self.sequence()?)];
+        while self.look == Token::UnionOperator {
+            self.match_next(Token::UnionOperator)?;
+            sequences.push(Tree(self.sequence()?));
         }
+        let sequences = sequences.into_iter().map(Tree::into_node).collect();
+        Ok(nest(sequences, Node::Union))
     }
 
     /// sequence := sub_sequence | ''
+    /// An empty sequence matches the empty string, so e.g. `(|a)` matches "" and "a".
     fn sequence(&mut self) -> Result<Node> {
         match &self.look {
-            Token::LeftParen | Token::Character(_) => self.sub_sequence(),
+            Token::LeftParen
+            | Token::LeftBracket
+            | Token::Character(_)
+            | Token::ClassEscape(_)
+            | Token::Dot
+            | Token::Caret
+            | Token::Dollar => self.sub_sequence(),
             _ => Ok(Node::Empty),
         }
     }
 
     /// sub_sequence := factor_set sub_sequence | star
     fn sub_sequence(&mut self) -> Result<Node> {
-        let star = self.factor_set();
-        match &self.look {
-            Token::LeftParen | Token::Character(_) => Ok(Node::Concat(
-                Box::new(star?),
-                Box::new(self.sub_sequence()?),
-            )),
-            _ => star,
+        // The factors are collected and nested at the end, like the sequences in `sub_expression`.
+        let mut stars = vec![Tree(self.factor_set()?)];
+        while let Token::LeftParen
+        | Token::LeftBracket
+        | Token::Character(_)
+        | Token::ClassEscape(_)
+        | Token::Dot
+        | Token::Caret
+        | Token::Dollar = &self.look
+        {
+            stars.push(Tree(self.factor_set()?));
         }
+        let stars = stars.into_iter().map(Tree::into_node).collect();
+        Ok(nest(stars, Node::Concat))
     }
 
-    /// factor_set := factor '*' | factor '+' | factor
+    /// factor_set := factor '*' | factor '+' | factor '?' | factor '{' repetition '}' | factor
+    ///
+    /// A quantifier applies to the factor right before it, so `ab*` is `a(b*)`. Quantifiers can
+    /// not be stacked, e.g. `a**`, as `a*?` and `a*+` are lazy and possessive in other flavors.
+    /// A group has to be used instead, e.g. `(a*)?`.
     fn factor_set(&mut self) -> Result<Node> {
-        let factor = self.factor();
-        match &self.look {
+        let factor = Tree(self.factor()?);
+        let quantified = is_quantifier(&self.look);
+        let node = match &self.look {
             Token::StarOperator => {
                 self.match_next(Token::StarOperator)?;
-                Ok(Node::Star(Box::new(factor?)))
+                Ok(Node::Star(Box::new(factor.into_node())))
             }
             Token::PlusOperator => {
                 self.match_next(Token::PlusOperator)?;
-                let factor = factor?;
+                let factor = factor.into_node();
                 Ok(Node::Concat(
                     Box::new(factor.clone()),
                     Box::new(Node::Star(Box::new(factor))),
                 ))
             }
-            _ => factor,
+            Token::QuestionOperator => {
+                self.match_next(Token::QuestionOperator)?;
+                Ok(Node::Union(
+                    Box::new(factor.into_node()),
+                    Box::new(Node::Empty),
+                ))
+            }
+            Token::LeftBrace => {
+                let span = self.lexer.token_start();
+                self.match_next(Token::LeftBrace)?;
+                let (min, max) = self.repetition()?;
+                self.match_next(Token::RightBrace)?;
+                let copies = max.unwrap_or(min + 1) as usize;
+                if copies * factor.0.node_count() > MAX_REPETITION_NODES {
+                    return Err(RegexError::RepetitionTooLarge { span });
+                }
+                let factor = factor.into_node();
+                // A nullable X matches every fewer copies of itself as well, so X{n,m} is X{m}
+                // and X{n,} is X*, which avoids copies that can be skipped in two ways.
+                let (min, max) = match factor.is_nullable() {
+                    true => (max.unwrap_or(0), max),
+                    false => (min, max),
+                };
+
+                // X{n,m} := X ... X (X(X ... (X)?)?)?
+                // X{n,}  := X ... X X*
+                let mut nodes = vec![factor.clone(); min as usize];
+                match max {
+                    Some(max) => {
+                        // The optional copies are nested, so that skipping the rest is one step
+                        // instead of one for each copy.
+                        let optional = (min..max).fold(None, |rest, _| {
+                            let node = match rest {
+                                Some(rest) => {
+                                    Node::Concat(Box::new(factor.clone()), Box::new(rest))
+                                }
+                                None => factor.clone(),
+                            };
+                            Some(Node::Union(Box::new(node), Box::new(Node::Empty)))
+                        });
+                        nodes.extend(optional);
+                    }
+                    None => nodes.push(Node::Star(Box::new(factor))),
+                }
+                Ok(nest(nodes, Node::Concat))
+            }
+            _ => Ok(factor.into_node()),
+        }?;
+        if quantified && is_quantifier(&self.look) {
+            drop_tree(node);
+            return Err(RegexError::Unsupported {
+                reason: STACKED_QUANTIFIER,
+                span: self.lexer.token_start(),
+            });
+        }
+        Ok(node)
+    }
+
+    /// repetition := number | number ',' | number ',' number | ',' number
+    fn repetition(&mut self) -> Result<(u32, Option<u32>)> {
+        let span = self.lexer.token_start();
+        let min = self.number()?;
+        if self.look != Token::Comma {
+            return match min {
+                Some(min) => Ok((min, Some(min))),
+                None => Err(unexpected_token(
+                    &[Token::Digit(0), Token::Comma],
+                    self.look,
+                    self.lexer.token_start(),
+                )),
+            };
+        }
+        self.match_next(Token::Comma)?;
+        let max = self.number()?;
+        match (min, max) {
+            (None, None) => Err(unexpected_token(
+                &[Token::Digit(0)],
+                self.look,
+                self.lexer.token_start(),
+            )),
+            (Some(min), Some(max)) if min > max => {
+                Err(RegexError::InvalidRepetition { min, max, span })
+            }
+            (min, max) => Ok((min.unwrap_or(0), max)),
+        }
+    }
+
+    /// number := Digit number | Digit | ''
+    fn number(&mut self) -> Result<Option<u32>> {
+        let span = self.lexer.token_start();
+        let mut ret = None;
+        while let Token::Digit(digit) = self.look {
+            self.match_next(Token::Digit(digit))?;
+            let number = ret
+                .unwrap_or(0u32)
+                .checked_mul(10)
+                .and_then(|n| n.checked_add(digit))
+                .filter(|n| *n <= MAX_REPETITION);
+            match number {
+                Some(number) => ret = Some(number),
+                None => return Err(RegexError::RepetitionTooLarge { span }),
+            }
         }
+        Ok(ret)
     }
 
-    /// factor := '(' subexpr ')' | Character
+    /// factor := '(' subexpr ')' | '[' class ']' | '.' | '^' | '$' | ClassEscape | Character
     fn factor(&mut self) -> Result<Node> {
         match &self.look {
             Token::LeftParen => {
                 self.match_next(Token::LeftParen)?;
-                let node = self.sub_expression();
+                let node = Tree(self.sub_expression()?);
                 self.match_next(Token::RightParen)?;
-                node
+                Ok(node.into_node())
+            }
+            Token::LeftBracket => {
+                self.match_next(Token::LeftBracket)?;
+                let node = self.class()?;
+                self.match_next(Token::RightBracket)?;
+                Ok(node)
+            }
+            Token::Dot => {
+                self.match_next(Token::Dot)?;
+                Ok(Node::AnyChar)
+            }
+            Token::Caret => {
+                self.match_next(Token::Caret)?;
+                Ok(Node::StartAnchor)
+            }
+            Token::Dollar => {
+                self.match_next(Token::Dollar)?;
+                Ok(Node::EndAnchor)
+            }
+            Token::ClassEscape(char) => {
+                let char = *char;
+                self.match_next(Token::ClassEscape(char))?;
+                match char.is_ascii_uppercase() {
+                    true => Ok(Node::NegatedClass(class_escape_chars(char))),
+                    false => Ok(Node::Class(class_escape_chars(char))),
+                }
             }
             Token::Character(char) => {
                 let node = Node::Character(*char);
                 self.match_next(Token::Character(*char))?;
                 Ok(node)
             }
-            other => Err(error_msg(
-                &[Token::LeftParen, Token::Character('_')],
+            other => Err(unexpected_token(
+                &[
+                    Token::LeftParen,
+                    Token::LeftBracket,
+                    Token::Dot,
+                    Token::Caret,
+                    Token::Dollar,
+                    Token::ClassEscape('_'),
+                    Token::Character('_'),
+                ],
                 *other,
+                self.lexer.token_start(),
             )),
         }
     }
+
+    /// class := class_item class | class_item
+    /// class_item := Character '-' Character | Character | '-' | ClassEscape
+    fn class(&mut self) -> Result<Node> {
+        let mut chars = BTreeSet::new();
+        loop {
+            match &self.look {
+                Token::Character(char) => {
+                    let first = *char;
+                    let span = self.lexer.token_start();
+                    self.match_next(Token::Character(first))?;
+                    if self.look != Token::Hyphen {
+                        chars.insert(first);
+                        continue;
+                    }
+                    self.match_next(Token::Hyphen)?;
+                    match &self.look {
+                        Token::Character(char) => {
+                            let last = *char;
+                            if first > last {
+                                return Err(RegexError::InvalidRange { first, last, span });
+                            }
+                            self.match_next(Token::Character(last))?;
+                            chars.extend(first..=last);
+                        }
+                        // A trailing '-' (e.g. `[a-]`) is a literal hyphen.
+                        _ => {
+                            chars.insert(first);
+                            chars.insert('-');
+                        }
+                    }
+                }
+                Token::Hyphen => {
+                    self.match_next(Token::Hyphen)?;
+                    chars.insert('-');
+                }
+                Token::ClassEscape(char) if char.is_ascii_uppercase() => {
+                    return Err(RegexError::NegatedClassEscapeInClass {
+                        char: *char,
+                        span: self.lexer.token_start(),
+                    });
+                }
+                Token::ClassEscape(char) => {
+                    let char = *char;
+                    self.match_next(Token::ClassEscape(char))?;
+                    chars.extend(class_escape_chars(char));
+                }
+                other if chars.is_empty() => {
+                    return Err(unexpected_token(
+                        &[Token::Character('_')],
+                        *other,
+                        self.lexer.token_start(),
+                    ));
+                }
+                _ => break,
+            }
+        }
+        match chars.len() {
+            1 => Ok(Node::Character(*chars.first().unwrap())),
+            _ => Ok(Node::Class(chars)),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::error::*;
     use crate::lexer::*;
     use crate::parser::*;
+    use alloc::collections::BTreeSet;
 
     #[test]
     fn expression() {
-        let mut parser = Parser::new(Lexer::new(r"a|(bc)*"));
+        let mut parser = Parser::new(Lexer::new(r"a|(bc)*")).unwrap();
         assert_eq!(
             parser.expression(),
             Ok(Node::Union(
@@ -155,7 +718,7 @@ mod tests {
 
     #[test]
     fn expression2() {
-        let mut parser = Parser::new(Lexer::new(r"a|"));
+        let mut parser = Parser::new(Lexer::new(r"a|")).unwrap();
         assert_eq!(
             parser.expression(),
             Ok(Node::Union(
@@ -167,7 +730,7 @@ mod tests {
 
     #[test]
     fn expression3() {
-        let mut parser = Parser::new(Lexer::new(r"(a|b)+"));
+        let mut parser = Parser::new(Lexer::new(r"(a|b)+")).unwrap();
         // (a|b)(a|b)*
         assert_eq!(
             parser.expression(),
@@ -184,11 +747,306 @@ mod tests {
         );
     }
 
+    #[test]
+    fn expression4() {
+        let mut parser = Parser::new(Lexer::new(r"a?b?c?")).unwrap();
+        let optional = |char| {
+            Box::new(Node::Union(
+                Box::new(Node::Character(char)),
+                Box::new(Node::Empty),
+            ))
+        };
+        assert_eq!(
+            parser.expression(),
+            Ok(Node::Concat(
+                optional('a'),
+                Box::new(Node::Concat(optional('b'), optional('c')))
+            ))
+        );
+    }
+
+    #[test]
+    fn class() {
+        let mut parser1 = Parser::new(Lexer::new(r"[bca]")).unwrap();
+        let mut parser2 = Parser::new(Lexer::new(r"[a]")).unwrap();
+        assert_eq!(
+            parser1.expression(),
+            Ok(Node::Class(['a', 'b', 'c'].into()))
+        );
+        assert_eq!(parser2.expression(), Ok(Node::Character('a')));
+    }
+
+    #[test]
+    fn class_range() {
+        let mut parser1 = Parser::new(Lexer::new(r"[a-cx0-2]")).unwrap();
+        let mut parser2 = Parser::new(Lexer::new(r"[-a]")).unwrap();
+        let mut parser3 = Parser::new(Lexer::new(r"[a-]")).unwrap();
+        let mut parser4 = Parser::new(Lexer::new(r"[ぁ-ぃ]")).unwrap();
+        assert_eq!(
+            parser1.expression(),
+            Ok(Node::Class(['a', 'b', 'c', 'x', '0', '1', '2'].into()))
+        );
+        assert_eq!(parser2.expression(), Ok(Node::Class(['-', 'a'].into())));
+        assert_eq!(parser3.expression(), Ok(Node::Class(['-', 'a'].into())));
+        assert_eq!(
+            parser4.expression(),
+            Ok(Node::Class(['ぁ', 'あ', 'ぃ'].into()))
+        );
+    }
+
+    #[test]
+    fn repetition() {
+        let optional = || Node::Union(Box::new(Node::Character('a')), Box::new(Node::Empty));
+        let mut parser1 = Parser::new(Lexer::new(r"a{2}")).unwrap();
+        let mut parser2 = Parser::new(Lexer::new(r"a{1,2}")).unwrap();
+        let mut parser3 = Parser::new(Lexer::new(r"a{1,}")).unwrap();
+        let mut parser4 = Parser::new(Lexer::new(r"a{,1}")).unwrap();
+        let mut parser5 = Parser::new(Lexer::new(r"a{0}")).unwrap();
+        assert_eq!(
+            parser1.expression(),
+            Ok(Node::Concat(
+                Box::new(Node::Character('a')),
+                Box::new(Node::Character('a'))
+            ))
+        );
+        assert_eq!(
+            parser2.expression(),
+            Ok(Node::Concat(
+                Box::new(Node::Character('a')),
+                Box::new(optional())
+            ))
+        );
+        assert_eq!(
+            parser3.expression(),
+            Ok(Node::Concat(
+                Box::new(Node::Character('a')),
+                Box::new(Node::Star(Box::new(Node::Character('a'))))
+            ))
+        );
+        assert_eq!(parser4.expression(), Ok(optional()));
+        assert_eq!(parser5.expression(), Ok(Node::Empty));
+
+        // The optional copies are nested, and a nullable factor is not made optional again.
+        let parse = |pattern| crate::parse(pattern).unwrap();
+        assert_eq!(parse(r"a{0,3}").to_string(), r"(a(aa?)?)?");
+        assert_eq!(parse(r"(a?){1,3}"), parse(r"a?a?a?"));
+        assert_eq!(parse(r"(a*){2,}"), parse(r"(a*)*"));
+    }
+
+    #[test]
+    fn repetition_too_large() {
+        // These fail before any copy is made, instead of running out of memory or time.
+        for (pattern, span) in [
+            (r"a{4000000000}", 2),
+            (r"a{1001}", 2),
+            (r"a{0,1001}", 4),
+            (r"(a{1000}){1000}", 9),
+            (r"(a{0,100}){0,100}", 10),
+        ] {
+            let mut parser = Parser::new(Lexer::new(pattern)).unwrap();
+            assert_eq!(
+                parser.expression(),
+                Err(RegexError::RepetitionTooLarge { span }),
+                "{pattern}"
+            );
+        }
+        assert!(Parser::new(Lexer::new(r"a{1000}"))
+            .unwrap()
+            .expression()
+            .is_ok());
+    }
+
+    #[test]
+    fn class_escape() {
+        let mut parser1 = Parser::new(Lexer::new(r"\d")).unwrap();
+        let mut parser2 = Parser::new(Lexer::new(r"\S")).unwrap();
+        let mut parser3 = Parser::new(Lexer::new(r"[\d.]")).unwrap();
+        let digits = ('0'..='9').collect::<BTreeSet<_>>();
+        assert_eq!(parser1.expression(), Ok(Node::Class(digits.clone())));
+        assert_eq!(
+            parser2.expression(),
+            Ok(Node::NegatedClass(
+                [' ', '\t', '\n', '\r', '\x0B', '\x0C'].into()
+            ))
+        );
+        assert_eq!(
+            parser3.expression(),
+            Ok(Node::Class(digits.into_iter().chain(['.']).collect()))
+        );
+    }
+
+    #[test]
+    fn class_escaped_char() {
+        for (pattern, chars) in [
+            (r"[\]]", vec![']']),
+            (r"[\]\-]", vec![']', '-']),
+            (r"[\\]", vec!['\\']),
+            (r"[\^a]", vec!['^', 'a']),
+            (r"[\[\n]", vec!['[', '\n']),
+            (r"[a\-c]", vec!['a', '-', 'c']),
+        ] {
+            let mut parser = Parser::new(Lexer::new(pattern)).unwrap();
+            let expected = match chars.len() {
+                1 => Node::Character(chars[0]),
+                _ => Node::Class(chars.into_iter().collect()),
+            };
+            assert_eq!(parser.expression(), Ok(expected), "{pattern}");
+        }
+    }
+
     #[test]
     fn fail() {
-        let mut parser1 = Parser::new(Lexer::new(r"a("));
-        let mut parser2 = Parser::new(Lexer::new(r"a)"));
-        assert!(parser1.expression().is_err());
-        assert!(parser2.expression().is_err());
+        let mut parser1 = Parser::new(Lexer::new(r"a(")).unwrap();
+        let mut parser2 = Parser::new(Lexer::new(r"a)")).unwrap();
+        let mut parser3 = Parser::new(Lexer::new(r"[]")).unwrap();
+        let mut parser4 = Parser::new(Lexer::new(r"[a")).unwrap();
+        let mut parser5 = Parser::new(Lexer::new(r"[z-a]")).unwrap();
+        let mut parser6 = Parser::new(Lexer::new(r"a{3,1}")).unwrap();
+        let mut parser7 = Parser::new(Lexer::new(r"a{,}")).unwrap();
+        let mut parser8 = Parser::new(Lexer::new(r"a{x}")).unwrap();
+        let mut parser9 = Parser::new(Lexer::new(r"a{99999999999}")).unwrap();
+        let mut parser10 = Parser::new(Lexer::new(r"[\D]")).unwrap();
+        assert_eq!(
+            parser1.expression(),
+            Err(RegexError::UnbalancedParen { span: 2 })
+        );
+        assert_eq!(
+            parser2.expression(),
+            Err(RegexError::UnbalancedParen { span: 1 })
+        );
+        assert_eq!(
+            parser3.expression(),
+            Err(RegexError::EmptyClass { span: 1 })
+        );
+        assert!(parser4.expression().is_err());
+        assert_eq!(
+            parser5.expression(),
+            Err(RegexError::InvalidRange {
+                first: 'z',
+                last: 'a',
+                span: 1
+            })
+        );
+        assert_eq!(
+            parser6.expression(),
+            Err(RegexError::InvalidRepetition {
+                min: 3,
+                max: 1,
+                span: 2
+            })
+        );
+        assert!(parser7.expression().is_err());
+        assert_eq!(
+            parser8.expression(),
+            Err(RegexError::UnexpectedToken {
+                expected: vec!["Digit".to_string(), ",".to_string()],
+                found: "x".to_string(),
+                span: 2
+            })
+        );
+        assert_eq!(
+            parser9.expression(),
+            Err(RegexError::RepetitionTooLarge { span: 2 })
+        );
+        assert_eq!(
+            parser10.expression(),
+            Err(RegexError::NegatedClassEscapeInClass { char: 'D', span: 1 })
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde() {
+        let node = Parser::new(Lexer::new(r"a|(bc)*"))
+            .unwrap()
+            .parse()
+            .unwrap();
+        let json = serde_json::to_string(&node).unwrap();
+        assert_eq!(
+            json,
+            r#"{"Union":[{"Character":"a"},{"Star":{"Concat":[{"Character":"b"},{"Character":"c"}]}}]}"#
+        );
+        assert_eq!(serde_json::from_str::<Node>(&json).unwrap(), node);
+    }
+
+    #[test]
+    fn normalize() {
+        let parse = |pattern| Parser::new(Lexer::new(pattern)).unwrap().parse().unwrap();
+        let normalize = |pattern| parse(pattern).normalize();
+        // Star(Star(x)) -> Star(x)
+        assert_eq!(normalize(r"(a*)*"), parse(r"a*"));
+        assert_eq!(normalize(r"((a*)*)*"), parse(r"a*"));
+        // Union(x, x) -> x
+        assert_eq!(normalize(r"a|a"), parse(r"a"));
+        assert_eq!(normalize(r"(ab)*|(ab)*"), parse(r"(ab)*"));
+        // Concat(Empty, x) -> x, and Concat(x, Empty) -> x
+        assert_eq!(normalize(r"()a"), parse(r"a"));
+        assert_eq!(normalize(r"a()"), parse(r"a"));
+        assert_eq!(normalize(r"()()"), Node::Empty);
+        // Concatenations are nested to the right.
+        assert_eq!(normalize(r"(ab)(cd)"), parse(r"abcd"));
+        // The alternatives of a union are flattened and sorted.
+        assert_eq!(normalize(r"(c|a)|b"), parse(r"a|b|c"));
+        assert_eq!(normalize(r"b|(a|c)|()(a|b)"), parse(r"a|b|c"));
+        assert_eq!(normalize(r"x(b|a)*"), normalize(r"x(a|b)*"));
+        assert_eq!(normalize(r"a?").to_string(), r"a?");
+        assert_ne!(normalize(r"a*"), normalize(r"(a|)*"));
+        // Long chains are not recursed into.
+        let pattern = "a|".repeat(10_000) + "b";
+        assert_eq!(normalize(&pattern), parse(r"a|b"));
+    }
+
+    #[test]
+    fn is_nullable() {
+        let parse = |pattern| Parser::new(Lexer::new(pattern)).unwrap().parse().unwrap();
+        for pattern in [
+            r"", r"a*", r"a?", r"(a|b*)", r"a*b?", r"(a?)*", r"^$", r"(|a)b*",
+        ] {
+            assert!(parse(pattern).is_nullable(), "{pattern}");
+        }
+        for pattern in [
+            r"a", r".", r"\d", r"\D", r"a+", r"a*b", r"(a|b)", r"a{1,3}", r"^a",
+        ] {
+            assert!(!parse(pattern).is_nullable(), "{pattern}");
+        }
+        let node = parse(&"a?".repeat(50_000));
+        assert!(node.is_nullable());
+        drop_tree(node);
+    }
+
+    #[test]
+    fn display() {
+        let node = Node::Concat(
+            Box::new(Node::Character('a')),
+            Box::new(Node::Star(Box::new(Node::Character('b')))),
+        );
+        assert_eq!(node.to_string(), r"ab*");
+        let node = Node::Union(
+            Box::new(Node::Character('a')),
+            Box::new(Node::Character('b')),
+        );
+        assert_eq!(node.to_string(), r"a|b");
+        let node = Node::Star(Box::new(Node::Star(Box::new(Node::Empty))));
+        assert_eq!(node.to_string(), r"(()*)*");
+        let node = Node::NegatedClass(['a', 'b', 'c', 'x'].into());
+        assert_eq!(node.to_string(), r"[^a-cx]");
+
+        for (pattern, expected) in [
+            (r"a|(bc)*", r"a|(bc)*"),
+            (r"(a|b)c|d", r"(a|b)c|d"),
+            (r"a(b|)", r"ab?"),
+            (r"(ab)+c?", r"(ab)+c?"),
+            (r"(a*)?|(b?)*", r"(a*)?|(b?)*"),
+            (r"\(\*\)\\\.", r"\(\*\)\\\."),
+            (r"[-a-d\]\n]\D\w", r"[\n\-\]a-d]\D\w"),
+            (r"[ab]\x01", r"[ab]\u{1}"),
+            (r".(|a)", r".(|a)"),
+            (r"^a\$|\^b$", r"^a\$|\^b$"),
+        ] {
+            let node = Parser::new(Lexer::new(pattern)).unwrap().parse().unwrap();
+            assert_eq!(node.to_string(), expected);
+            let reparsed = Parser::new(Lexer::new(expected)).unwrap().parse().unwrap();
+            assert_eq!(reparsed, node, "{pattern}");
+        }
     }
 }
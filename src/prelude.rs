@@ -0,0 +1,11 @@
+//! The names the `std` prelude and collections provide, imported from `alloc` instead, and from
+//! `hashbrown` for the hash collections with the `no_std` feature.
+
+pub(crate) use alloc::boxed::Box;
+pub(crate) use alloc::string::{String, ToString};
+pub(crate) use alloc::vec::Vec;
+pub(crate) use alloc::{format, vec};
+#[cfg(feature = "no_std")]
+pub(crate) use hashbrown::{hash_map, HashMap, HashSet};
+#[cfg(not(feature = "no_std"))]
+pub(crate) use std::collections::{hash_map, HashMap, HashSet};
@@ -0,0 +1,161 @@
+use dfa_regex::{Backend, Regex, RegexBuilder, RegexError};
+
+#[test]
+fn builder01() {
+    let regex = RegexBuilder::new(r"(a|b)*Ab*")
+        .case_insensitive(true)
+        .minimize(true)
+        .build()
+        .unwrap();
+    assert!(regex.matches("bAaB"));
+    assert!(regex.matches("a"));
+    assert!(!regex.matches("bc"));
+    assert!(regex.equivalent(&Regex::new(r"(a|b|A|B)*(a|A)(b|B)*").unwrap()));
+    let unminimized = RegexBuilder::new(r"(a|b)*Ab*")
+        .case_insensitive(true)
+        .build()
+        .unwrap();
+    assert!(regex.state_count() < unminimized.state_count());
+}
+
+#[test]
+fn builder02() {
+    for backend in [Backend::Dfa, Backend::Nfa, Backend::LazyDfa] {
+        let regex = RegexBuilder::new(r"ab+").backend(backend).build().unwrap();
+        assert!(regex.matches("abb"), "{backend:?}");
+        assert!(!regex.matches("a"), "{backend:?}");
+    }
+    assert!(RegexBuilder::new(r"a(").build().is_err());
+}
+
+#[test]
+fn builder03() {
+    let regex = Regex::new(r"a.b").unwrap();
+    assert!(regex.matches("a.b"));
+    assert!(!regex.matches("a\nb"));
+    for backend in [Backend::Dfa, Backend::Nfa, Backend::LazyDfa] {
+        let regex = RegexBuilder::new(r"a.b")
+            .dot_matches_newline(true)
+            .backend(backend)
+            .build()
+            .unwrap();
+        assert!(regex.matches("a\nb"), "{backend:?}");
+        assert!(regex.matches("a.b"), "{backend:?}");
+    }
+    let regex = Regex::new(r"a\D+").unwrap();
+    assert!(regex.matches("a\nb"));
+}
+
+#[cfg(feature = "unicode-normalization")]
+#[test]
+fn builder04() {
+    use dfa_regex::Normalization;
+
+    let composed = "caf\u{e9}";
+    let decomposed = "cafe\u{301}";
+    assert!(!Regex::new(composed).unwrap().matches(decomposed));
+    for pattern in [composed, decomposed] {
+        let regex = RegexBuilder::new(pattern)
+            .normalize(Normalization::Nfc)
+            .build()
+            .unwrap();
+        assert!(regex.matches(composed), "{pattern:?}");
+        assert!(regex.matches(decomposed), "{pattern:?}");
+        assert!(!regex.matches("cafe"), "{pattern:?}");
+    }
+    let regex = RegexBuilder::new("[e\u{301}x]")
+        .normalize(Normalization::Nfc)
+        .build()
+        .unwrap();
+    assert!(regex.matches("\u{e9}"));
+    assert!(!regex.matches("e"));
+}
+
+#[test]
+fn builder05() {
+    for backend in [Backend::Dfa, Backend::Nfa, Backend::LazyDfa] {
+        let regex = RegexBuilder::new(r"cat")
+            .whole_word(true)
+            .backend(backend)
+            .build()
+            .unwrap();
+        assert_eq!(regex.find("a cat b"), Some((2, 5)), "{backend:?}");
+        assert!(regex.contains("cat"), "{backend:?}");
+        assert!(!regex.contains("category"), "{backend:?}");
+        assert!(!regex.contains("concat"), "{backend:?}");
+        assert_eq!(regex.match_ends("category"), vec![], "{backend:?}");
+        assert_eq!(regex.match_ends("cat concat cat_"), vec![3], "{backend:?}");
+        assert_eq!(
+            regex.find_iter("cat,cats,cat").collect::<Vec<_>>(),
+            vec![(0, 3), (9, 12)],
+            "{backend:?}"
+        );
+    }
+    assert!(Regex::new(r"cat").unwrap().contains("category"));
+    // The longest match ends inside a word, so a shorter one is found.
+    let regex = RegexBuilder::new(r"cat(s|ego)?")
+        .whole_word(true)
+        .build()
+        .unwrap();
+    assert_eq!(regex.find("a catego-ry"), Some((2, 8)));
+    assert_eq!(regex.find("a categories"), None);
+    assert_eq!(regex.find_shortest("cats"), Some((0, 4)));
+    let regex = RegexBuilder::new(r"cat$").whole_word(true).build().unwrap();
+    assert_eq!(regex.find("a cat"), Some((2, 5)));
+    assert_eq!(regex.find("a cat b"), None);
+}
+
+#[test]
+fn builder06() {
+    let pattern = r"(a|b)*a(a|b){8}";
+    let error = RegexBuilder::new(pattern)
+        .max_states(16)
+        .build()
+        .unwrap_err();
+    assert_eq!(error, RegexError::TooLarge { limit: 16 });
+    assert_eq!(error.to_string(), "The DFA needs more than 16 states");
+    let regex = RegexBuilder::new(pattern).max_states(1024).build().unwrap();
+    assert!(regex.matches("babbbbbbbb"));
+    assert!(!regex.matches("bbbbbbbbbb"));
+    for backend in [Backend::Nfa, Backend::LazyDfa] {
+        let regex = RegexBuilder::new(pattern)
+            .max_states(16)
+            .backend(backend)
+            .build();
+        assert!(regex.is_ok(), "{backend:?}");
+    }
+}
+
+#[cfg(feature = "unicode-normalization")]
+#[test]
+fn builder07() {
+    use dfa_regex::Normalization;
+
+    let regex = RegexBuilder::new("caf\u{e9}")
+        .normalize(Normalization::Nfc)
+        .build()
+        .unwrap();
+    let text = "a cafe\u{301}!";
+    assert_eq!(regex.find(text), Some((2, 8)));
+    assert_eq!(regex.find_shortest(text), Some((2, 8)));
+    assert!(regex.contains(text));
+    assert!(!regex.contains("a cafe"));
+    assert_eq!(regex.match_ends(text), vec![8]);
+    assert!(regex.is_match_at(text, 2));
+    assert!(regex.matches_prefix("cafe\u{301}!"));
+    assert!(regex.matches_suffix("a cafe\u{301}"));
+    assert_eq!(regex.consume("cafe\u{301}!"), Some(("cafe\u{301}", "!")));
+    assert_eq!(regex.replace_all(text, "tea"), "a tea!");
+    assert!(regex.matches_slice(&['c', 'a', 'f', 'e', '\u{301}']));
+    let chars = text.chars().chain(std::iter::from_fn(|| panic!()));
+    assert_eq!(regex.find_in_stream(chars), Some(7));
+
+    // A match ending inside the characters normalized together is widened to all of them.
+    let regex = RegexBuilder::new("\u{e9}")
+        .normalize(Normalization::Nfc)
+        .build()
+        .unwrap();
+    assert_eq!(regex.find("xe\u{301}\u{302}"), Some((1, 6)));
+    assert!(!regex.is_match_at("xe\u{301}\u{302}", 2));
+    assert_eq!(regex.find("e"), None);
+}
@@ -0,0 +1,274 @@
+use dfa_regex::{Backend, Regex, RegexBuilder};
+
+#[test]
+fn find01() {
+    let regex = Regex::new(r"cat").unwrap();
+    assert_eq!(regex.find("the cat sat"), Some((4, 7)));
+    assert_eq!(regex.find("cat"), Some((0, 3)));
+    assert_eq!(regex.find("the dog sat"), None);
+}
+
+#[test]
+fn find02() {
+    let regex = Regex::new(r"a+|ab+").unwrap();
+    assert_eq!(regex.find("xxabbbaa"), Some((2, 6)));
+}
+
+#[test]
+fn find03() {
+    let regex = Regex::new(r"猫+").unwrap();
+    assert_eq!(regex.find("この猫猫は"), Some((6, 12)));
+}
+
+#[test]
+fn find04() {
+    let regex = Regex::new(r"b*").unwrap();
+    assert_eq!(regex.find("abb"), Some((0, 0)));
+    assert_eq!(regex.find(""), Some((0, 0)));
+}
+
+#[test]
+fn find05() {
+    let regex = Regex::new(r"^cat").unwrap();
+    assert_eq!(regex.find("cat dog"), Some((0, 3)));
+    assert_eq!(regex.find("a cat"), None);
+    let regex = Regex::new(r"cat$").unwrap();
+    assert_eq!(regex.find("a cat"), Some((2, 5)));
+    assert_eq!(regex.find("cat dog"), None);
+    let regex = Regex::new(r"^$").unwrap();
+    assert_eq!(regex.find(""), Some((0, 0)));
+    assert_eq!(regex.find("a"), None);
+    let regex = Regex::new(r"\^\$").unwrap();
+    assert_eq!(regex.find("a^$"), Some((1, 3)));
+}
+
+#[test]
+fn find06() {
+    let regex = Regex::new(r"(^|b)a+").unwrap();
+    assert_eq!(
+        regex.find_iter("aabaxba").collect::<Vec<_>>(),
+        [(0, 2), (2, 4), (5, 7)]
+    );
+    let regex = Regex::new(r"a($|b)").unwrap();
+    assert_eq!(
+        regex.find_iter("abaca").collect::<Vec<_>>(),
+        [(0, 2), (4, 5)]
+    );
+    let regex = Regex::new(r"a^b|a$b").unwrap();
+    assert!(regex.is_empty_language());
+    assert_eq!(regex.find("ab"), None);
+    for regex in [Regex::new_nfa(r"^a|b$"), Regex::new_lazy(r"^a|b$")] {
+        let regex = regex.unwrap();
+        assert_eq!(
+            regex.find_iter("abab").collect::<Vec<_>>(),
+            [(0, 1), (3, 4)]
+        );
+        assert!(regex.matches_suffix("ab"));
+        assert!(!regex.matches_suffix("ba"));
+    }
+}
+
+#[test]
+fn find_iter01() {
+    let regex = Regex::new(r"a+").unwrap();
+    let matches = regex.find_iter("aa b aaa").collect::<Vec<_>>();
+    assert_eq!(matches, vec![(0, 2), (5, 8)]);
+}
+
+#[test]
+fn find_iter02() {
+    let regex = Regex::new(r"\d*").unwrap();
+    let matches = regex.find_iter("1a猫23").collect::<Vec<_>>();
+    assert_eq!(matches, vec![(0, 1), (1, 1), (2, 2), (5, 7), (7, 7)]);
+}
+
+#[test]
+fn find_iter03() {
+    let regex = Regex::new(r"x").unwrap();
+    assert_eq!(regex.find_iter("").count(), 0);
+    assert_eq!(regex.find_iter("abc").count(), 0);
+}
+
+#[test]
+fn match_indices01() {
+    let regex = Regex::new(r"ｗ+").unwrap();
+    let matches = regex.match_indices("ｗｗ ｗ").collect::<Vec<_>>();
+    assert_eq!(matches, vec![(0, 6, "ｗｗ"), (7, 10, "ｗ")]);
+    let regex = Regex::new(r"猫*").unwrap();
+    let matches = regex.match_indices("猫a").collect::<Vec<_>>();
+    assert_eq!(matches, vec![(0, 3, "猫"), (3, 3, ""), (4, 4, "")]);
+}
+
+#[test]
+fn is_match_at01() {
+    let regex = Regex::new(r"b+c").unwrap();
+    let text = "abbcd";
+    assert!(regex.is_match_at(text, 1));
+    assert!(regex.is_match_at(text, 2));
+    assert!(!regex.is_match_at(text, 0));
+    assert!(!regex.is_match_at(text, 3));
+    assert!(!regex.matches(text));
+    assert_eq!(regex.find(text), Some((1, 4)));
+}
+
+#[test]
+fn is_match_at02() {
+    let regex = Regex::new(r"猫*").unwrap();
+    let text = "犬猫";
+    assert!(regex.is_match_at(text, "犬".len()));
+    assert!(regex.is_match_at(text, 0));
+    assert!(regex.is_match_at(text, text.len()));
+}
+
+#[test]
+fn contains01() {
+    for regex in [
+        Regex::new(r"cat"),
+        Regex::new_nfa(r"cat"),
+        Regex::new_lazy(r"cat"),
+    ] {
+        let regex = regex.unwrap();
+        assert!(regex.contains("scatter"));
+        assert!(regex.contains("cat"));
+        assert!(!regex.contains("ca t"));
+        assert!(!regex.contains(""));
+    }
+    assert!(Regex::new(r"x*").unwrap().contains(""));
+    assert!(Regex::new(r"猫+").unwrap().contains("犬猫犬"));
+    let regex = Regex::new(r"^cat").unwrap();
+    assert!(regex.contains("cats"));
+    assert!(!regex.contains("scatter"));
+    let regex = Regex::new(r"ter$").unwrap();
+    assert!(regex.contains("scatter"));
+    assert!(!regex.contains("scatters"));
+}
+
+#[test]
+fn matches_suffix01() {
+    let regex = Regex::new(r"log").unwrap();
+    assert!(regex.matches_suffix("server.log"));
+    assert!(regex.matches_suffix("log"));
+    assert!(!regex.matches_suffix("server.log.1"));
+    assert!(!regex.matches_suffix("lo"));
+}
+
+#[test]
+fn matches_suffix02() {
+    for regex in [Regex::new(r"\.(rs|toml)"), Regex::new_nfa(r"\.(rs|toml)")] {
+        let regex = regex.unwrap();
+        assert!(regex.matches_suffix("Cargo.toml"));
+        assert!(regex.matches_suffix("src/lib.rs"));
+        assert!(!regex.matches_suffix("lib.rs.bak"));
+    }
+    assert!(Regex::new(r"a*").unwrap().matches_suffix("xyz"));
+}
+
+#[test]
+fn consume01() {
+    let regex = Regex::new(r"a+").unwrap();
+    assert_eq!(regex.consume("aaab"), Some(("aaa", "b")));
+    assert_eq!(regex.consume("aaa"), Some(("aaa", "")));
+    assert_eq!(regex.consume("baaa"), None);
+    let regex = Regex::new(r"\s*").unwrap();
+    assert_eq!(regex.consume("猫 "), Some(("", "猫 ")));
+    assert_eq!(regex.consume(" \t猫"), Some((" \t", "猫")));
+}
+
+#[test]
+fn find_shortest01() {
+    for regex in [
+        Regex::new(r"a+"),
+        Regex::new_nfa(r"a+"),
+        Regex::new_lazy(r"a+"),
+    ] {
+        let regex = regex.unwrap();
+        assert_eq!(regex.find("aaa"), Some((0, 3)));
+        assert_eq!(regex.find_shortest("aaa"), Some((0, 1)));
+        assert_eq!(regex.find_shortest("baa"), Some((1, 2)));
+        assert_eq!(regex.find_shortest("bbb"), None);
+    }
+    let regex = Regex::new(r"a|ab").unwrap();
+    assert_eq!(regex.find("ab"), Some((0, 2)));
+    assert_eq!(regex.find_shortest("ab"), Some((0, 1)));
+    let regex = Regex::new(r"a+$").unwrap();
+    assert_eq!(regex.find("baab"), None);
+    assert_eq!(regex.find_shortest("baa"), Some((1, 3)));
+}
+
+#[test]
+fn match_ends01() {
+    for regex in [
+        Regex::new(r"aa"),
+        Regex::new_nfa(r"aa"),
+        Regex::new_lazy(r"aa"),
+    ] {
+        let regex = regex.unwrap();
+        assert_eq!(regex.match_ends("aaaa"), vec![2, 3, 4]);
+        assert_eq!(
+            regex.find_iter("aaaa").collect::<Vec<_>>(),
+            vec![(0, 2), (2, 4)]
+        );
+        assert_eq!(regex.match_ends("abaa"), vec![4]);
+    }
+    assert_eq!(Regex::new(r"a*").unwrap().match_ends("ba"), vec![0, 1, 2]);
+    assert_eq!(Regex::new(r"^aa").unwrap().match_ends("aaaa"), vec![2]);
+    assert_eq!(Regex::new(r"a+$").unwrap().match_ends("aaaa"), vec![4]);
+}
+
+#[test]
+fn find_in_stream01() {
+    // The characters after the match would panic if they were consumed.
+    let stream = |text: &'static str| text.chars().chain(std::iter::from_fn(|| panic!()));
+    for regex in [
+        Regex::new(r"ab+"),
+        Regex::new_nfa(r"ab+"),
+        Regex::new_lazy(r"ab+"),
+    ] {
+        let regex = regex.unwrap();
+        assert_eq!(regex.find_in_stream(stream("猫xab")), Some(4));
+        assert_eq!(regex.find_in_stream("xaxb".chars()), None);
+    }
+    assert_eq!(
+        Regex::new(r"b*").unwrap().find_in_stream(stream("")),
+        Some(0)
+    );
+    let regex = Regex::new(r"ab$").unwrap();
+    assert_eq!(regex.find_in_stream("abxab".chars()), Some(5));
+    assert_eq!(regex.find_in_stream("abx".chars()), None);
+}
+
+#[test]
+fn find_in_stream02() {
+    // With `^`, `$` or whole words, only the character after the match is consumed.
+    let stream = |text: &'static str| text.chars().chain(std::iter::from_fn(|| panic!()));
+    for backend in [Backend::Dfa, Backend::Nfa, Backend::LazyDfa] {
+        let build = |pattern| RegexBuilder::new(pattern).backend(backend).build().unwrap();
+        let regex = build(r"^ab");
+        assert_eq!(regex.find_in_stream(stream("abx")), Some(2));
+        assert_eq!(regex.find_in_stream("xab".chars()), None);
+        let regex = build(r"b$|xa");
+        assert_eq!(regex.find_in_stream(stream("abxay")), Some(4));
+        assert_eq!(regex.find_in_stream("abb".chars()), Some(3));
+        let regex = RegexBuilder::new(r"cat")
+            .whole_word(true)
+            .backend(backend)
+            .build()
+            .unwrap();
+        assert_eq!(regex.find_in_stream(stream("concat cat x")), Some(10));
+        assert_eq!(regex.find_in_stream("cats".chars()), None);
+    }
+    for pattern in [r"^a|b$", r"(^|x)a+", r"a(b|$)", r"^$", r"b*$"] {
+        let regex = Regex::new(pattern).unwrap();
+        for text in ["", "a", "ab", "xaab", "bxa", "bab"] {
+            let expected = regex
+                .match_ends(text)
+                .first()
+                .map(|end| text[..*end].chars().count());
+            assert_eq!(
+                regex.find_in_stream(text.chars()),
+                expected,
+                "{pattern} {text}"
+            );
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use dfa_regex::Regex;
+use dfa_regex::{BudgetExceeded, CharMapper, Regex, RunResult};
 
 #[test]
 fn case01() {
@@ -69,3 +69,547 @@ fn case08() {
     assert!(!regex.matches(r" qwertyuiopasdfghjklzxcvbnm"));
     assert!(!regex.matches(r"qwertyuiopasdfghjklzxcvbn"));
 }
+
+#[test]
+fn case09() {
+    let regex = Regex::new(r"ab?c").unwrap();
+    assert!(regex.matches(r"abc"));
+    assert!(regex.matches(r"ac"));
+    assert!(!regex.matches(r"abbc"));
+}
+
+#[test]
+fn case10() {
+    let regex = Regex::new(r"colou?r\?").unwrap();
+    assert!(regex.matches(r"color?"));
+    assert!(regex.matches(r"colour?"));
+    assert!(!regex.matches(r"colour"));
+}
+
+#[test]
+fn case11() {
+    let regex = Regex::new(r"a.c").unwrap();
+    assert!(regex.matches(r"abc"));
+    assert!(regex.matches(r"axc"));
+    assert!(regex.matches(r"a猫c"));
+    assert!(regex.matches(r"a.c"));
+    assert!(!regex.matches(r"ac"));
+    assert!(!regex.matches(r"abbc"));
+}
+
+#[test]
+fn case12() {
+    let regex = Regex::new(r"(a|.b)*\.").unwrap();
+    assert!(regex.matches(r"."));
+    assert!(regex.matches(r"aab."));
+    assert!(regex.matches(r"山bab."));
+    assert!(!regex.matches(r"a山."));
+    assert!(!regex.matches(r"ab"));
+}
+
+#[test]
+fn case13() {
+    let regex = Regex::new(r"[xyz]").unwrap();
+    assert!(regex.matches(r"x"));
+    assert!(regex.matches(r"y"));
+    assert!(regex.matches(r"z"));
+    assert!(!regex.matches(r"w"));
+    assert!(!regex.matches(r"xy"));
+}
+
+#[test]
+fn case14() {
+    let regex = Regex::new(r"gr[ae]y[*\]]+").unwrap();
+    assert!(regex.matches(r"gray*"));
+    assert!(regex.matches(r"grey]*]"));
+    assert!(!regex.matches(r"gry*"));
+    assert!(!regex.matches(r"grey"));
+}
+
+#[test]
+fn case15() {
+    let regex = Regex::new(r"[a-c]").unwrap();
+    assert!(regex.matches(r"b"));
+    assert!(!regex.matches(r"d"));
+    assert!(!regex.matches(r"-"));
+}
+
+#[test]
+fn case16() {
+    let regex = Regex::new(r"0x[a-fA-F0-9]+").unwrap();
+    assert!(regex.matches(r"0xdeadBEEF"));
+    assert!(regex.matches(r"0x0123456789"));
+    assert!(!regex.matches(r"0x"));
+    assert!(!regex.matches(r"0xg"));
+}
+
+#[test]
+fn case17() {
+    let regex = Regex::new(r"a{2,3}").unwrap();
+    assert!(regex.matches(r"aa"));
+    assert!(regex.matches(r"aaa"));
+    assert!(!regex.matches(r"a"));
+    assert!(!regex.matches(r"aaaa"));
+}
+
+#[test]
+fn case18() {
+    let regex = Regex::new(r"(ab){2,}c{,1}").unwrap();
+    assert!(regex.matches(r"abab"));
+    assert!(regex.matches(r"abababc"));
+    assert!(!regex.matches(r"abc"));
+    assert!(!regex.matches(r"ababcc"));
+}
+
+#[test]
+fn case19() {
+    let regex = Regex::new(r"\d\d").unwrap();
+    assert!(regex.matches(r"42"));
+    assert!(!regex.matches(r"4"));
+    assert!(!regex.matches(r"4a"));
+}
+
+#[test]
+fn case20() {
+    let regex = Regex::new(r"\w+\s\\d\D\W\S").unwrap();
+    assert!(regex.matches(r"snake_case1 \da-x"));
+    assert!(regex.matches("x\t\\dあ!猫"));
+    assert!(!regex.matches(r"x da-x"));
+    assert!(!regex.matches(r"x \d1-x"));
+    assert!(!regex.matches(r"x \da_x"));
+    assert!(!regex.matches(r"x \da- "));
+}
+
+#[test]
+fn case21() {
+    let regex = Regex::new(r"a\tb\r?\n").unwrap();
+    assert!(regex.matches("a\tb\n"));
+    assert!(regex.matches("a\tb\r\n"));
+    assert!(!regex.matches(r"atbn"));
+    assert!(!regex.matches(r"a\tb\n"));
+}
+
+#[test]
+fn case22() {
+    let regex = Regex::new(r"\x61[\x30-\x39]").unwrap();
+    assert!(regex.matches(r"a0"));
+    assert!(regex.matches(r"a9"));
+    assert!(!regex.matches(r"x61"));
+    assert!(Regex::new(r"\xZZ").is_err());
+    assert!(Regex::new(r"a\x4").is_err());
+}
+
+#[test]
+fn case23() {
+    let regex = Regex::new(r"\u{5C71}\u{1F600}+").unwrap();
+    assert!(regex.matches(r"山😀"));
+    assert!(regex.matches(r"山😀😀"));
+    assert!(!regex.matches(r"山"));
+    assert!(Regex::new(r"\u{DFFF}").is_err());
+}
+
+#[test]
+fn case24() {
+    let regex = Regex::new(r"ab*").unwrap();
+    assert!(regex.is_match("abbb"));
+    assert!(!regex.is_match("abbbc"));
+    assert_eq!(regex.match_len("abbbc"), Some("abbb".len()));
+    assert_eq!(regex.match_len("a"), Some(1));
+    assert_eq!(regex.match_len("ba"), None);
+}
+
+#[test]
+fn case25() {
+    let regex = Regex::new(r"猫*").unwrap();
+    assert_eq!(regex.match_len("猫猫犬"), Some("猫猫".len()));
+    assert_eq!(regex.match_len("犬"), Some(0));
+}
+
+#[test]
+fn case26() {
+    let regex: Regex = r"(ab)+".parse().unwrap();
+    assert!(regex.matches(r"abab"));
+    assert!(!regex.matches(r"aba"));
+    assert!(r"(ab".parse::<Regex>().is_err());
+}
+
+#[test]
+fn case27() {
+    use dfa_regex::Node;
+    let regex = Regex::from_node(Node::Star(Box::new(Node::Character('a'))));
+    assert!(regex.matches(r"aaa"));
+    assert!(regex.matches(r""));
+    assert!(!regex.matches(r"ab"));
+    let regex = Regex::from_node(Node::Concat(
+        Box::new(Node::Class(['x', 'y'].into())),
+        Box::new(Node::NegatedClass(['z'].into())),
+    ));
+    assert!(regex.matches(r"x猫"));
+    assert!(!regex.matches(r"yz"));
+}
+
+#[test]
+fn case28() {
+    for (pattern, texts) in [
+        (
+            r"(p(erl|ython|hp)|ruby)",
+            &["python", "ruby", "ruby2", ""][..],
+        ),
+        (r"(a|.b)*\.", &[".", "aab.", "山bab.", "a山.", "ab"][..]),
+        (r"\Dx|\d{2,3}", &["zx", "1x", "12", "1234", "猫x"][..]),
+        (r"(ab|ba)+", &["ab", "baabba", "babab", "", "b"][..]),
+    ] {
+        let dfa = Regex::new(pattern).unwrap();
+        let nfa = Regex::new_nfa(pattern).unwrap();
+        for text in texts {
+            assert_eq!(dfa.matches(text), nfa.matches(text), "{pattern} on {text}");
+            assert_eq!(
+                dfa.match_len(text),
+                nfa.match_len(text),
+                "{pattern} on {text}"
+            );
+        }
+    }
+}
+
+#[test]
+fn case29() {
+    for (pattern, texts) in [
+        (
+            r"(p(erl|ython|hp)|ruby)",
+            &["python", "ruby", "ruby2", ""][..],
+        ),
+        (r"(a|.b)*\.", &[".", "aab.", "山bab.", "a山.", "ab"][..]),
+        (r"(a|b)*a(a|b){3}", &["abbb", "bbabab", "aabba", "abb"][..]),
+    ] {
+        let eager = Regex::new(pattern).unwrap();
+        let lazy = Regex::new_lazy(pattern).unwrap();
+        // Twice, to match against both newly built and cached states.
+        for text in texts.iter().chain(texts) {
+            assert_eq!(
+                eager.matches(text),
+                lazy.matches(text),
+                "{pattern} on {text}"
+            );
+            assert_eq!(
+                eager.match_len(text),
+                lazy.match_len(text),
+                "{pattern} on {text}"
+            );
+        }
+    }
+}
+
+#[test]
+fn case30() {
+    let regex = Regex::new(r"(a|b)*c\D*\d").unwrap();
+    let mut text = "ab".repeat(100_000);
+    text.push('c');
+    text.push_str(&"猫x".repeat(100_000));
+    assert!(!regex.matches(&text));
+    text.push('7');
+    assert!(regex.matches(&text));
+    text.push('7');
+    assert!(!regex.matches(&text));
+}
+
+#[test]
+fn case31() {
+    let regex = Regex::new_case_insensitive(r"Hello").unwrap();
+    assert!(regex.matches(r"HELLO"));
+    assert!(regex.matches(r"hello"));
+    assert!(regex.matches(r"hElLo"));
+    assert!(!regex.matches(r"help"));
+    let regex = Regex::new_case_insensitive(r"[a-c]\D.ẞ").unwrap();
+    assert!(regex.matches(r"B!yẞ"));
+    assert!(regex.matches(r"a-ßß"));
+    assert!(!regex.matches(r"dxyß"));
+    assert!(!regex.matches(r"a1yß"));
+    assert!(!regex.matches(r"aa9SS"));
+}
+
+#[test]
+fn case32() {
+    let pattern = "abcde".repeat(10_000);
+    let regex = Regex::new(&pattern).unwrap();
+    assert!(regex.matches(&pattern));
+    assert!(!regex.matches(&pattern[1..]));
+    let pattern = ["x"; 10_000].join("|");
+    let regex = Regex::new(&pattern).unwrap();
+    assert!(regex.matches("x"));
+}
+
+#[test]
+fn case33() {
+    let regex = Regex::new(r"a(b|c)+").unwrap();
+    assert!(regex.matches_chars("abc".chars()));
+    assert!(regex.matches_chars(['a', 'c', 'c']));
+    assert!(!regex.matches_chars("ab".chars().rev()));
+    assert!(!regex.matches_chars(std::iter::empty()));
+
+    // Stops reading at 'x', where the regex can no longer match.
+    let mut chars = "abxbc".chars();
+    assert!(!regex.matches_chars(chars.by_ref()));
+    assert_eq!(chars.as_str(), "bc");
+
+    // Reads from a generator that is never materialized as a string.
+    let counter = (0..1000).map(|i| if i == 0 { 'a' } else { 'b' });
+    assert!(regex.matches_chars(counter));
+}
+
+#[test]
+fn case34() {
+    let regex = Regex::new(r"a.c").unwrap();
+    assert!(regex.matches_bytes(b"abc"));
+    assert!(regex.matches_bytes("a猫c".as_bytes()));
+    assert!(!regex.matches_bytes(b"ac"));
+    assert!(!regex.matches_bytes(b"a\xffc"));
+    assert!(!regex.matches_bytes(b"a\xe7\x8cc"));
+}
+
+#[test]
+fn case35() {
+    assert_eq!(Regex::escape(r"a(b)*"), r"a\(b\)\*");
+    for text in [r"a(b)*", r"1+1=2?", r"[a-z]{1,2}.|\", r"猫\d"] {
+        let regex = Regex::new(&Regex::escape(text)).unwrap();
+        assert!(regex.matches(text), "{text}");
+        assert!(
+            !regex.matches(&text.chars().skip(1).collect::<String>()),
+            "{text}"
+        );
+    }
+}
+
+#[test]
+fn case36() {
+    let regex = Regex::new(r"(|a)").unwrap();
+    assert!(regex.matches(r""));
+    assert!(regex.matches(r"a"));
+    assert!(!regex.matches(r"aa"));
+    let regex = Regex::new(r"(|||||a)").unwrap();
+    assert!(regex.matches(r""));
+    assert!(regex.matches(r"a"));
+    let regex = Regex::new(r"x(((|)|(|(|a)))|)*y").unwrap();
+    assert!(regex.matches(r"xy"));
+    assert!(regex.matches(r"xaay"));
+    assert!(!regex.matches(r"xby"));
+    let regex = Regex::new(r"|").unwrap();
+    assert!(regex.matches(r""));
+    assert!(!regex.matches(r"a"));
+    let regex = Regex::new(r"a||b|").unwrap();
+    assert!(regex.matches(r""));
+    assert!(regex.matches(r"b"));
+    assert!(regex.equivalent(&Regex::new(r"(a|b)?").unwrap()));
+    let pattern = format!("{}a{}", "(|".repeat(200), ")".repeat(200));
+    assert!(Regex::new(&pattern)
+        .unwrap()
+        .equivalent(&Regex::new(r"a?").unwrap()));
+}
+
+#[test]
+fn case37() {
+    let regex = Regex::new(r"(?:ab)+").unwrap();
+    assert!(regex.matches(r"abab"));
+    assert!(!regex.matches(r"aba"));
+    assert!(regex.equivalent(&Regex::new(r"(ab)+").unwrap()));
+    let regex = Regex::new(r"x(?:a|(?:b|c)d)?").unwrap();
+    assert!(regex.matches(r"x"));
+    assert!(regex.matches(r"xcd"));
+    assert!(!regex.matches(r"xc"));
+    let regex = Regex::new(r"\(?").unwrap();
+    assert!(regex.matches(r"("));
+    assert!(regex.matches(r""));
+    assert!(Regex::new(r"(?=a)").is_err());
+    assert!(Regex::new(r"(?i)a").is_err());
+    assert!(Regex::new(r"(?").is_err());
+}
+
+#[test]
+fn case38() {
+    let text = "ab".repeat(1 << 19);
+    let regex = Regex::new(r"(ab)*c?").unwrap();
+    assert!(regex.matches(&text));
+    assert!(regex.matches(&(text.clone() + "c")));
+    assert!(!regex.matches(&(text.clone() + "a")));
+    assert!(!regex.matches(&(text.clone() + "あ")));
+    let text = "aあ".repeat(1 << 18);
+    let regex = Regex::new(r"(.あ)*").unwrap();
+    assert!(regex.matches(&text));
+    assert!(!regex.matches(&text[1..]));
+}
+
+#[test]
+fn case39() {
+    let regex = Regex::new(r"^ab$").unwrap();
+    assert!(regex.matches(r"ab"));
+    assert!(!regex.matches(r"xab"));
+    assert!(regex.equivalent(&Regex::new(r"ab").unwrap()));
+    let regex = Regex::new(r"a*^b").unwrap();
+    assert!(regex.matches(r"b"));
+    assert!(!regex.matches(r"ab"));
+    let regex = Regex::new(r"a$b*").unwrap();
+    assert!(regex.matches(r"a"));
+    assert!(!regex.matches(r"ab"));
+    let regex = Regex::new(r"\^a\$").unwrap();
+    assert!(regex.matches(r"^a$"));
+    assert_eq!(Regex::escape(r"^a$"), r"\^a\$");
+}
+
+#[test]
+fn case40() {
+    let text = "a".repeat(100_000);
+    for regex in [
+        Regex::new(r"a*"),
+        Regex::new_nfa(r"a*"),
+        Regex::new_lazy(r"a*"),
+    ] {
+        let regex = regex.unwrap();
+        let error = regex.matches_with_budget(&text, 1000).unwrap_err();
+        assert_eq!(error, BudgetExceeded { max_steps: 1000 });
+        assert_eq!(regex.matches_with_budget(&text, 100_000), Ok(true));
+        assert_eq!(regex.matches_with_budget("", 0), Ok(true));
+        assert_eq!(regex.matches_with_budget(&format!("b{text}"), 1), Ok(false));
+    }
+}
+
+#[test]
+fn case41() {
+    // Once "a" is read, no string can follow it to make a match.
+    let regex = Regex::new(r"a(.|\n)*").unwrap().complement();
+    let text = "a".to_string() + &"b".repeat(1_000_000);
+    assert!(!regex.matches(&text));
+    assert!(regex.matches(&text[1..]));
+    assert_eq!(regex.matches_with_budget(&text, 2), Ok(false));
+    assert_eq!(regex.match_len(&text), Some(0));
+}
+
+#[test]
+fn case42() {
+    let words = (0..20_000).map(|i| format!("w{i}")).collect::<Vec<_>>();
+    let pattern = words.join("|");
+    let regex = Regex::new(&pattern).unwrap();
+    assert!(regex.matches("w0"));
+    assert!(regex.matches("w19999"));
+    assert!(!regex.matches("w20000"));
+    assert!(!regex.matches("w"));
+}
+
+#[test]
+fn case43() {
+    let regex = Regex::new_tagged(&["ab", "a"]).unwrap();
+    assert_eq!(regex.match_tag("a"), Some(1));
+    assert_eq!(regex.match_tag("ab"), Some(0));
+    assert_eq!(regex.match_tag("b"), None);
+    assert!(regex.matches("a") && regex.matches("ab"));
+    let regex = Regex::new_tagged(&["a*", "a", "[a-z]+"]).unwrap();
+    assert_eq!(regex.match_tag(""), Some(0));
+    assert_eq!(regex.match_tag("a"), Some(0));
+    assert_eq!(regex.match_tag("ab"), Some(2));
+    let regex = Regex::new_tagged(&["^x", "y$"]).unwrap();
+    assert_eq!(regex.match_tag("y"), Some(1));
+    assert_eq!(regex.find("axby"), Some((3, 4)));
+    assert_eq!(Regex::new("ab").unwrap().match_tag("ab"), Some(0));
+    assert!(Regex::new_tagged(&["a", "("]).is_err());
+}
+
+#[test]
+fn case44() {
+    for regex in [Regex::new(r"abc(de)*"), Regex::new_nfa(r"abc(de)*")] {
+        let regex = regex.unwrap();
+        let run = |accepted, stuck, consumed| RunResult {
+            accepted,
+            stuck,
+            consumed,
+        };
+        assert_eq!(regex.run("abxc"), run(false, true, 2));
+        assert_eq!(regex.run("abcd"), run(false, false, 4));
+        assert_eq!(regex.run("abcde"), run(true, false, 5));
+        assert_eq!(regex.run(""), run(false, false, 0));
+        assert_eq!(regex.run("abcdee"), run(false, true, 5));
+    }
+}
+
+#[test]
+fn case45() {
+    struct FullWidthDigits;
+    impl CharMapper for FullWidthDigits {
+        fn map(&self, char: char) -> char {
+            match char {
+                '０'..='９' => char::from_u32(char as u32 - '０' as u32 + '0' as u32).unwrap(),
+                _ => char,
+            }
+        }
+    }
+    let regex = Regex::new(r"\d{3}-\d{4}").unwrap();
+    assert!(!regex.matches("１２３-４５６７"));
+    assert!(regex.matches_with_mapper("１２３-４５６７", &FullWidthDigits));
+    assert!(regex.matches_with_mapper("12３-4567", &FullWidthDigits));
+    assert!(!regex.matches_with_mapper("１２３４５６７", &FullWidthDigits));
+    let regex = Regex::new(r"w+").unwrap();
+    let mapper = |char| if char == 'ｗ' { 'w' } else { char };
+    assert!(regex.matches_with_mapper("wｗw", &mapper));
+}
+
+#[test]
+fn case46() {
+    for pattern in ["a\0b", r"a\0b", "a[\0]b", r"a[\0]b", "a.b"] {
+        let regex = Regex::new(pattern).unwrap();
+        assert!(regex.matches("a\0b"), "{pattern:?}");
+        assert!(!regex.matches("a0b") || pattern == "a.b", "{pattern:?}");
+        assert!(!regex.matches("ab"), "{pattern:?}");
+    }
+    let regex = Regex::new("\0*").unwrap();
+    assert!(regex.matches(""));
+    assert!(regex.matches("\0\0\0"));
+    assert!(!regex.matches("\\0"));
+    assert_eq!(Regex::new("x\0").unwrap().find("ax\0b"), Some((1, 3)));
+    assert!(Regex::new("\\\0").unwrap().matches("\0"));
+}
+
+#[test]
+fn case47() {
+    for regex in [Regex::new(r"a(b|c)*"), Regex::new_lazy(r"a(b|c)*")] {
+        let regex = regex.unwrap();
+        assert!(regex.matches_slice(&['a', 'b', 'c']));
+        assert!(regex.matches_slice(&['a']));
+        assert!(!regex.matches_slice(&[]));
+        assert!(!regex.matches_slice(&['a', 'b', 'd']));
+    }
+    let chars = "猫ab".chars().collect::<Vec<_>>();
+    assert!(Regex::new(r"猫\w+").unwrap().matches_slice(&chars));
+}
+
+#[test]
+fn case48() {
+    let regex = Regex::new(r"[\]\-\\^]+").unwrap();
+    assert!(regex.matches("]-\\^"));
+    assert!(regex.matches("^"));
+    assert!(!regex.matches("a"));
+    let regex = Regex::new(r"[\d.]+").unwrap();
+    assert!(regex.matches("3.14"));
+    assert!(!regex.matches("3,14"));
+    let regex = Regex::new(r"[a\-z]").unwrap();
+    assert!(regex.matches("-"));
+    assert!(!regex.matches("b"));
+}
+
+#[test]
+fn case49() {
+    for regex in [
+        Regex::new(r"ab"),
+        Regex::new_nfa(r"ab"),
+        Regex::new_lazy(r"ab"),
+    ] {
+        let regex = regex.unwrap();
+        assert!(!regex.matches_full("abc"));
+        assert!(regex.matches_prefix("abc"));
+        assert!(regex.matches_full("ab"));
+        assert!(regex.matches_prefix("ab"));
+        assert!(!regex.matches_prefix("a"));
+        assert!(!regex.matches_prefix("xab"));
+        assert_eq!(regex.matches("abc"), regex.matches_full("abc"));
+    }
+    let regex = Regex::new(r"ab$").unwrap();
+    assert!(!regex.matches_prefix("abc"));
+    assert!(regex.matches_prefix("ab"));
+}
@@ -0,0 +1,17 @@
+//! With the `no_std` feature, the library is built without `std`, using `alloc` and `hashbrown`.
+#![cfg(feature = "no_std")]
+
+use dfa_regex::{MultiRegex, Regex};
+
+#[test]
+fn no_std01() {
+    let regex = Regex::new(r"(p(erl|ython|hp)|ruby)").unwrap();
+    assert!(regex.matches("python"));
+    assert!(!regex.matches("java"));
+    assert_eq!(regex.find("I like ruby"), Some((7, 11)));
+    let regex = Regex::new_lazy(r"(a|b)*c").unwrap();
+    assert!(regex.matches("abac"));
+    assert!(!regex.matches("abab"));
+    let multi = MultiRegex::new(&[("if", 0), ("[a-z]+", 1)]).unwrap();
+    assert_eq!(multi.match_longest("if"), Some((2, 0)));
+}
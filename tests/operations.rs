@@ -0,0 +1,512 @@
+use dfa_regex::{
+    parse, Automaton, NondeterministicFiniteAutomaton, Regex, RegexBuilder, RegexStats,
+};
+
+#[test]
+fn complement01() {
+    let regex = Regex::new(r"abc").unwrap().complement();
+    assert!(!regex.matches("abc"));
+    assert!(regex.matches("abd"));
+    assert!(regex.matches("ab"));
+    assert!(regex.matches("abcd"));
+    assert!(regex.matches(""));
+}
+
+#[test]
+fn complement02() {
+    let regex = Regex::new(r"a.*").unwrap().complement();
+    assert!(!regex.matches("a"));
+    assert!(!regex.matches("a猫"));
+    assert!(regex.matches("猫a"));
+    assert!(regex.matches(""));
+}
+
+#[test]
+fn complement03() {
+    let regex = Regex::new(r"\d+").unwrap().complement().complement();
+    assert!(regex.matches("42"));
+    assert!(!regex.matches("4a"));
+    assert!(!regex.matches(""));
+}
+
+#[test]
+fn intersect01() {
+    let regex1 = Regex::new(r"(a|b)*").unwrap();
+    let regex2 = Regex::new(r"a*").unwrap();
+    let regex = regex1.intersect(&regex2);
+    assert!(regex.matches("aaa"));
+    assert!(regex.matches(""));
+    assert!(!regex.matches("ab"));
+    assert!(!regex.matches("b"));
+}
+
+#[test]
+fn intersect02() {
+    let regex1 = Regex::new(r".*a.*").unwrap();
+    let regex2 = Regex::new(r"\w\w\w").unwrap();
+    let regex = regex1.intersect(&regex2);
+    assert!(regex.matches("xay"));
+    assert!(regex.matches("aaa"));
+    assert!(!regex.matches("xyz"));
+    assert!(!regex.matches("xa"));
+    assert!(!regex.matches("x猫a"));
+}
+
+#[test]
+fn symmetric_difference01() {
+    let regex1 = Regex::new(r"a*").unwrap();
+    let regex2 = Regex::new(r"aa*").unwrap();
+    let regex = regex1.symmetric_difference(&regex2);
+    assert!(regex.matches(""));
+    assert!(!regex.matches("a"));
+    assert!(!regex.matches("aaa"));
+    assert!(!regex.matches("b"));
+    assert_eq!(regex.shortest_match(), Some("".to_string()));
+    let regex = Regex::new(r"ab|cd").unwrap();
+    let regex = regex.symmetric_difference(&Regex::new(r"cd|ef").unwrap());
+    assert!(regex.matches("ab") && regex.matches("ef"));
+    assert!(!regex.matches("cd"));
+    assert!(regex.symmetric_difference(&regex).is_empty_language());
+}
+
+#[test]
+fn difference_witness01() {
+    let regex1 = Regex::new(r"ab").unwrap();
+    let regex2 = Regex::new(r"a.b").unwrap();
+    assert_eq!(regex1.difference_witness(&regex2), Some("ab".to_string()));
+    let regex1 = Regex::new(r"a[xy]b").unwrap();
+    let witness = regex1.difference_witness(&regex2).unwrap();
+    assert_eq!(witness.chars().count(), 3);
+    assert!(regex2.matches(&witness) && !regex1.matches(&witness));
+    let regex2 = Regex::new(r"a(y|x)b").unwrap();
+    assert_eq!(regex1.difference_witness(&regex2), None);
+}
+
+#[test]
+fn equivalent01() {
+    let regex = Regex::new(r"(a|b)").unwrap();
+    assert!(regex.equivalent(&Regex::new(r"(b|a)").unwrap()));
+    assert!(regex.equivalent(&Regex::new(r"[ab]").unwrap()));
+    assert!(!regex.equivalent(&Regex::new(r"ab").unwrap()));
+    assert!(!regex.equivalent(&Regex::new(r"(a|b|c)").unwrap()));
+}
+
+#[test]
+fn equivalent02() {
+    let regex = Regex::new(r"(a*b*)*").unwrap();
+    assert!(regex.equivalent(&Regex::new(r"(a|b)*").unwrap()));
+    assert!(!regex.equivalent(&Regex::new(r".*").unwrap()));
+    assert!(Regex::new(r"(.|\n)*")
+        .unwrap()
+        .equivalent(&Regex::new(r"(\d|\D)*").unwrap()));
+    assert!(!Regex::new(r".*")
+        .unwrap()
+        .equivalent(&Regex::new(r"(\d|\D)*").unwrap()));
+    assert!(!Regex::new(r"a")
+        .unwrap()
+        .equivalent(&Regex::new(r"a").unwrap().complement()));
+}
+
+#[test]
+fn eq01() {
+    let regex = Regex::new(r"(a|b)").unwrap();
+    assert!(regex == Regex::new(r"(b|a)").unwrap());
+    assert!(regex == Regex::new_nfa(r"[ab]").unwrap());
+    assert!(regex != Regex::new(r"(a|b|c)").unwrap());
+}
+
+#[test]
+fn is_subset_of01() {
+    let regex = Regex::new(r"abc").unwrap();
+    assert!(regex.is_subset_of(&Regex::new(r"a.c").unwrap()));
+    assert!(regex.is_subset_of(&regex));
+    assert!(!Regex::new(r"a.c").unwrap().is_subset_of(&regex));
+    assert!(!Regex::new(r"a*")
+        .unwrap()
+        .is_subset_of(&Regex::new(r"a").unwrap()));
+    assert!(Regex::new(r"a")
+        .unwrap()
+        .is_subset_of(&Regex::new(r"a*").unwrap()));
+}
+
+#[test]
+fn is_empty_language01() {
+    let regex1 = Regex::new(r"a").unwrap();
+    let regex2 = Regex::new(r"b").unwrap();
+    assert!(!regex1.is_empty_language());
+    assert!(!Regex::new(r"").unwrap().is_empty_language());
+    assert!(regex1.intersect(&regex2).is_empty_language());
+    assert!(!Regex::new(r"a*").unwrap().complement().is_empty_language());
+    assert!(Regex::new(r"(.|\n)*")
+        .unwrap()
+        .complement()
+        .is_empty_language());
+}
+
+#[test]
+fn is_infinite01() {
+    assert!(!Regex::new(r"abc").unwrap().is_infinite());
+    assert!(!Regex::new(r"a{2,5}|b?").unwrap().is_infinite());
+    assert!(Regex::new(r"a*").unwrap().is_infinite());
+    assert!(Regex::new(r"(ab)+").unwrap().is_infinite());
+    assert!(Regex::new(r"x.*").unwrap().is_infinite());
+}
+
+#[test]
+fn is_infinite02() {
+    let regex = Regex::new(r"a").unwrap().complement();
+    assert!(regex.is_infinite());
+    // The only cycle is through the sink state, which can not reach an accept state.
+    assert!(!regex.complement().is_infinite());
+}
+
+#[test]
+fn shortest_match01() {
+    let regex = Regex::new(r"(hello|hi)").unwrap();
+    assert_eq!(regex.shortest_match(), Some("hi".to_string()));
+    let regex = Regex::new(r"a*").unwrap();
+    assert_eq!(regex.shortest_match(), Some("".to_string()));
+    let regex = Regex::new(r"x\d{3}").unwrap();
+    assert_eq!(regex.shortest_match(), Some("x000".to_string()));
+}
+
+#[test]
+fn shortest_match02() {
+    let regex1 = Regex::new(r"a").unwrap();
+    let regex2 = Regex::new(r"b").unwrap();
+    assert_eq!(regex1.intersect(&regex2).shortest_match(), None);
+    let regex = Regex::new(r"a+").unwrap().complement();
+    assert_eq!(regex.shortest_match(), Some("".to_string()));
+    let regex = Regex::new(r"|a.*").unwrap().complement();
+    assert_eq!(regex.shortest_match(), Some("b".to_string()));
+    let regex = Regex::new(r"b|\D").unwrap();
+    assert_eq!(regex.shortest_match(), Some("a".to_string()));
+    let regex = Regex::new(r"0|\D").unwrap();
+    assert_eq!(regex.shortest_match(), Some("0".to_string()));
+}
+
+#[test]
+fn literal_prefix01() {
+    let regex = Regex::new(r"foo(bar|qux)").unwrap();
+    assert_eq!(regex.literal_prefix(), Some("foo".to_string()));
+    let regex = Regex::new(r"foo(bar|baz)").unwrap();
+    assert_eq!(regex.literal_prefix(), Some("fooba".to_string()));
+    let regex = Regex::new(r"hello").unwrap();
+    assert_eq!(regex.literal_prefix(), Some("hello".to_string()));
+    let regex = Regex::new(r"ab(c|d)*").unwrap();
+    assert_eq!(regex.literal_prefix(), Some("ab".to_string()));
+    let regex = Regex::new(r"(ab|ac)d").unwrap();
+    assert_eq!(regex.literal_prefix(), Some("a".to_string()));
+    assert_eq!(Regex::new(r"a*b").unwrap().literal_prefix(), None);
+    assert_eq!(Regex::new(r".x").unwrap().literal_prefix(), None);
+    assert_eq!(Regex::new(r"[ab]c").unwrap().literal_prefix(), None);
+}
+
+#[test]
+fn transitions01() {
+    let regex = Regex::new(r"a(b|c)").unwrap();
+    let dfa = regex.dfa();
+    assert_eq!(dfa.transitions().count(), 3);
+    assert_eq!(dfa.default_transitions().count(), 0);
+    let (from, _, to) = dfa.transitions().find(|(_, char, _)| *char == 'a').unwrap();
+    assert_eq!(from, dfa.start());
+    assert!(!dfa.accepts().contains(&to));
+    assert_eq!(dfa.accepts().len(), 2);
+}
+
+#[test]
+fn to_bytes01() {
+    let regex = Regex::new(r"(a|b)*c").unwrap();
+    let regex = Regex::from_bytes(&regex.to_bytes()).unwrap();
+    assert!(regex.matches("c"));
+    assert!(regex.matches("abbac"));
+    assert!(!regex.matches("ab"));
+    assert!(!regex.matches("abcc"));
+    assert!(Regex::from_bytes(b"abc").is_err());
+    // A huge state count in a few bytes is rejected instead of being allocated.
+    let mut bytes = b"DFA\x01\xFE\xFF\xFF\xFF".to_vec();
+    bytes.extend([0; 16]);
+    assert!(Regex::from_bytes(&bytes).is_err());
+}
+
+#[test]
+fn concat01() {
+    let regex = Regex::new(r"ab").unwrap();
+    let regex = regex.concat(&Regex::new(r"cd").unwrap());
+    assert!(regex.matches("abcd"));
+    assert!(!regex.matches("ab"));
+    assert!(!regex.matches("cd"));
+    assert!(!regex.matches("abcdcd"));
+}
+
+#[test]
+fn concat02() {
+    let regex1 = Regex::new(r"\D*").unwrap();
+    let regex2 = Regex::new_nfa(r"\d+").unwrap();
+    let regex = regex1.concat(&regex2);
+    assert!(regex.matches("猫x42"));
+    assert!(regex.matches("4"));
+    assert!(!regex.matches("4x"));
+    assert!(regex.equivalent(&Regex::new(r"\D*\d+").unwrap()));
+    assert!(regex2
+        .concat(&regex1)
+        .equivalent(&Regex::new(r"\d+\D*").unwrap()));
+}
+
+#[test]
+fn union01() {
+    let regex = Regex::new(r"a").unwrap();
+    let regex = regex.union(&Regex::new(r"b").unwrap());
+    assert!(regex.matches("a"));
+    assert!(regex.matches("b"));
+    assert!(!regex.matches("ab"));
+    assert!(!regex.matches(""));
+}
+
+#[test]
+fn union02() {
+    let regex = Regex::new(r"\d+").unwrap();
+    let regex = regex.union(&Regex::new(r"\D").unwrap().complement());
+    assert!(regex.equivalent(&Regex::new(r"\d+|(|(\d|\D)(\d|\D)+|\d)").unwrap()));
+}
+
+#[test]
+fn star01() {
+    let regex = Regex::new(r"ab").unwrap().star();
+    assert!(regex.matches("abab"));
+    assert!(regex.matches(""));
+    assert!(!regex.matches("aba"));
+    assert!(regex.equivalent(&Regex::new(r"(ab)*").unwrap()));
+    let regex = Regex::new(r".b").unwrap().star();
+    assert!(regex.equivalent(&Regex::new(r"(.b)*").unwrap()));
+}
+
+#[test]
+fn count_matches_of_length01() {
+    let regex = Regex::new(r"(a|b)").unwrap();
+    assert_eq!(regex.count_matches_of_length(0), 0);
+    assert_eq!(regex.count_matches_of_length(1), 2);
+    assert_eq!(regex.count_matches_of_length(2), 0);
+    let regex = Regex::new(r"(a|b|c)*d?").unwrap();
+    assert_eq!(regex.count_matches_of_length(0), 1);
+    assert_eq!(regex.count_matches_of_length(3), 27 + 9);
+    assert_eq!(regex.count_matches_of_length(50), u64::MAX);
+}
+
+#[test]
+fn count_matches_of_length02() {
+    assert_eq!(
+        Regex::new(r".").unwrap().count_matches_of_length(1),
+        1_112_063
+    );
+    assert_eq!(
+        Regex::new(r"\D").unwrap().count_matches_of_length(1),
+        1_112_054
+    );
+    assert_eq!(
+        Regex::new(r"a\Db").unwrap().count_matches_of_length(3),
+        1_112_054
+    );
+    let regex = Regex::new(r"\d").unwrap().complement();
+    assert_eq!(regex.count_matches_of_length(1), 1_112_054);
+}
+
+#[test]
+#[cfg(feature = "rand")]
+fn sample01() {
+    use rand::SeedableRng;
+    let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+    let regex = Regex::new(r"(cat|dog)").unwrap();
+    for _ in 0..100 {
+        let sample = regex.sample(&mut rng, 10).unwrap();
+        assert!(sample == "cat" || sample == "dog", "{sample}");
+    }
+    assert_eq!(regex.sample(&mut rng, 2), None);
+}
+
+#[test]
+#[cfg(feature = "rand")]
+fn sample02() {
+    use rand::SeedableRng;
+    let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+    let regex = Regex::new(r"a*\Db(c|.)*").unwrap();
+    for _ in 0..100 {
+        let sample = regex.sample(&mut rng, 8).unwrap();
+        assert!(sample.chars().count() <= 8, "{sample}");
+        assert!(regex.matches(&sample), "{sample}");
+    }
+    assert_eq!(
+        Regex::new(r"a").unwrap().complement().sample(&mut rng, 0),
+        Some(String::new())
+    );
+    assert_eq!(
+        Regex::new(r"a")
+            .unwrap()
+            .intersect(&Regex::new(r"b").unwrap())
+            .sample(&mut rng, 5),
+        None
+    );
+}
+
+#[test]
+fn reverse01() {
+    let regex = Regex::new(r"abc").unwrap().reverse();
+    assert!(regex.matches("cba"));
+    assert!(!regex.matches("abc"));
+    let regex = Regex::new(r"a(b|cd)*\De").unwrap().reverse();
+    assert!(regex.equivalent(&Regex::new(r"e\D(b|dc)*a").unwrap()));
+    assert!(regex
+        .reverse()
+        .equivalent(&Regex::new(r"a(b|cd)*\De").unwrap()));
+}
+
+#[test]
+fn alphabet01() {
+    let regex = Regex::new(r"(a|b)c").unwrap();
+    assert_eq!(regex.alphabet(), ['a', 'b', 'c'].into());
+    assert_eq!(Regex::new(r"a.").unwrap().alphabet(), ['\n', 'a'].into());
+    assert_eq!(
+        Regex::new(r"a\D").unwrap().alphabet(),
+        ['0', '1', '2', '3', '4', '5', '6', '7', '8', '9', 'a'].into()
+    );
+    assert_eq!(Regex::new(r"").unwrap().alphabet(), [].into());
+}
+
+#[test]
+fn stats01() {
+    // The start state and one state for each character, all of which transition by every
+    // character into the state for it.
+    let regex = Regex::new(r"(a|b|c)*").unwrap();
+    assert_eq!(
+        regex.stats(),
+        RegexStats {
+            num_states: 4,
+            num_transitions: 12,
+            num_accept_states: 4,
+            alphabet_size: 3,
+            is_minimal: false,
+        }
+    );
+    let regex = RegexBuilder::new(r"(a|b|c)*")
+        .minimize(true)
+        .build()
+        .unwrap();
+    assert_eq!(
+        regex.stats(),
+        RegexStats {
+            num_states: 1,
+            num_transitions: 3,
+            num_accept_states: 1,
+            alphabet_size: 3,
+            is_minimal: true,
+        }
+    );
+    assert_eq!(Regex::new(r"a.").unwrap().stats().num_transitions, 3);
+}
+
+#[test]
+fn debug01() {
+    let regex = Regex::new(r"ab|b").unwrap();
+    let dfa = regex.dfa();
+    let debug = format!("{regex:?}");
+    assert!(debug.starts_with("Regex { backend: Dfa, "), "{debug}");
+    assert!(
+        debug.contains(&format!("start: {}", dfa.start().0)),
+        "{debug}"
+    );
+    assert!(debug.contains(r#"alphabet: "ab""#), "{debug}");
+    assert_eq!(debug, format!("{:?}", Regex::new(r"ab|b").unwrap()));
+    let debug = format!("{:?}", Regex::new_nfa(r"ab|b").unwrap());
+    assert!(debug.starts_with("Regex { backend: Nfa, "), "{debug}");
+    assert!(debug.contains("start: "), "{debug}");
+}
+
+#[test]
+fn clone01() {
+    for regex in [
+        Regex::new(r"ab+"),
+        Regex::new_nfa(r"ab+"),
+        Regex::new_lazy(r"^ab+"),
+    ] {
+        let regex = regex.unwrap();
+        assert!(regex.matches("abb"));
+        let clone = regex.clone();
+        assert!(clone.matches("abbb"));
+        assert!(!clone.matches("a"));
+        assert!(regex.matches("ab"));
+        assert_eq!(clone.find("xabb"), regex.find("xabb"));
+    }
+}
+
+#[test]
+fn send_sync01() {
+    use std::sync::Arc;
+    use std::thread;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Regex>();
+
+    for regex in [Regex::new(r"[0-9]+"), Regex::new_lazy(r"[0-9]+")] {
+        let regex = Arc::new(regex.unwrap());
+        let handles = (0..4)
+            .map(|i| {
+                let regex = Arc::clone(&regex);
+                thread::spawn(move || regex.matches(&i.to_string()) && !regex.matches("x"))
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            assert!(handle.join().unwrap());
+        }
+    }
+}
+
+#[test]
+fn anchored_operations01() {
+    let regex = Regex::new(r"^ab").unwrap().reverse();
+    assert!(regex.matches("ba"));
+    assert_eq!(regex.find("bax"), None);
+    assert_eq!(regex.find("xba"), Some((1, 3)));
+
+    let regex = Regex::new(r"^a").unwrap().union(&Regex::new(r"b").unwrap());
+    assert_eq!(regex.find("xa"), None);
+    assert_eq!(regex.find("xab"), Some((2, 3)));
+    assert_eq!(regex.find("ax"), Some((0, 1)));
+
+    let regex = Regex::new(r"^ab")
+        .unwrap()
+        .intersect(&Regex::new(r"ab").unwrap());
+    assert_eq!(regex.find("xab"), None);
+    assert_eq!(regex.find("abx"), Some((0, 2)));
+
+    let regex = Regex::new(r"^a")
+        .unwrap()
+        .concat(&Regex::new(r"b").unwrap());
+    assert!(regex.matches("ab"));
+    assert_eq!(regex.find("xab"), None);
+    assert_eq!(regex.find("abx"), Some((0, 2)));
+    let regex = Regex::new(r"a")
+        .unwrap()
+        .concat(&Regex::new(r"b$").unwrap());
+    assert_eq!(regex.find("abx"), None);
+    assert_eq!(regex.find("xab"), Some((1, 3)));
+
+    let regex = Regex::new(r"^a").unwrap().star();
+    assert!(regex.matches("a"));
+    assert!(!regex.matches("aa"));
+    assert_eq!(regex.find("aab"), Some((0, 1)));
+    assert_eq!(regex.find("ba"), Some((0, 0)));
+    let regex = Regex::new(r"a|b$").unwrap().star();
+    assert_eq!(regex.find("aab"), Some((0, 3)));
+    assert_eq!(regex.find("abab"), Some((0, 1)));
+}
+
+#[test]
+fn nfa_accessors01() {
+    let nfa = NondeterministicFiniteAutomaton::from_node(parse(r"ab").unwrap());
+    let start = nfa.epsilon_closure(nfa.start());
+    assert!(start.contains(&nfa.start()));
+    assert!(start.iter().all(|state| !nfa.accepts().contains(state)));
+    let states = nfa.step(&nfa.start_state(), 'a').unwrap();
+    let states = nfa.step(&states, 'b').unwrap();
+    assert!(states.iter().any(|state| nfa.accepts().contains(state)));
+}
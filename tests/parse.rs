@@ -0,0 +1,118 @@
+use dfa_regex::{parse, Node, Regex, RegexError};
+
+#[test]
+fn parse01() {
+    assert_eq!(
+        parse(r"a|b"),
+        Ok(Node::Union(
+            Box::new(Node::Character('a')),
+            Box::new(Node::Character('b'))
+        ))
+    );
+}
+
+#[test]
+fn parse02() {
+    assert_eq!(
+        parse(r"(a\d)*"),
+        Ok(Node::Star(Box::new(Node::Concat(
+            Box::new(Node::Character('a')),
+            Box::new(Node::Class(('0'..='9').collect()))
+        ))))
+    );
+    assert_eq!(parse(r"a)"), Err(RegexError::UnbalancedParen { span: 1 }));
+}
+
+#[test]
+fn parse03() {
+    let a = || Box::new(Node::Character('a'));
+    let b = || Box::new(Node::Character('b'));
+    assert_eq!(
+        parse(r"ab*"),
+        Ok(Node::Concat(a(), Box::new(Node::Star(b()))))
+    );
+    assert_eq!(
+        parse(r"(ab)*"),
+        Ok(Node::Star(Box::new(Node::Concat(a(), b()))))
+    );
+    for (pattern, span) in [
+        (r"a**", 2),
+        (r"a*+", 2),
+        (r"xa+?", 3),
+        (r"a{2}*", 4),
+        (r"a?{2}", 2),
+    ] {
+        assert!(
+            matches!(parse(pattern), Err(RegexError::Unsupported { span: s, .. }) if s == span),
+            "{pattern}"
+        );
+    }
+    assert_eq!(parse(r"(a*)*"), Ok(Node::Star(Box::new(Node::Star(a())))));
+    assert!(parse(r"(a+)?").is_ok());
+}
+
+#[test]
+fn display01() {
+    use dfa_regex::Regex;
+    let pattern = parse(r"a|(bc)*").unwrap().to_string();
+    let regex = Regex::new(&pattern).unwrap();
+    assert!(regex.equivalent(&Regex::new(r"a|(bc)*").unwrap()));
+}
+
+#[test]
+fn parse04() {
+    for pattern in [r"[^a]", r"x[^a-c]y", r"[^]"] {
+        assert!(
+            matches!(parse(pattern), Err(RegexError::Unsupported { .. })),
+            "{pattern}"
+        );
+    }
+    let class = |chars: &[char]| Ok(Node::Class(chars.iter().cloned().collect()));
+    assert_eq!(parse(r"[\^a]"), class(&['^', 'a']));
+    assert_eq!(parse(r"[a^]"), class(&['^', 'a']));
+    let regex = Regex::new(r"[\^a]").unwrap();
+    assert!(regex.matches("^"));
+    assert!(regex.matches("a"));
+    assert!(!regex.matches("b"));
+    assert_eq!(
+        parse(&parse(r"[\^a]").unwrap().to_string()),
+        parse(r"[\^a]")
+    );
+}
+
+#[test]
+fn display_negated_class01() {
+    for escape in ['D', 'W', 'S'] {
+        let pattern = format!("\\{escape}");
+        let node = parse(&pattern).unwrap();
+        assert!(matches!(node, Node::NegatedClass(_)));
+        assert_eq!(node.to_string(), pattern);
+        assert_eq!(parse(&node.to_string()), Ok(node));
+    }
+    let node = Node::NegatedClass(['a', 'b', 'c', 'x'].into());
+    assert_eq!(node.to_string(), r"[^a-cx]");
+    assert!(matches!(
+        parse(&node.to_string()),
+        Err(RegexError::Unsupported { span: 0, .. })
+    ));
+    let node = Node::NegatedClass(['^', 'a'].into());
+    assert_eq!(node.to_string(), r"[^\^a]");
+    assert!(parse(&node.to_string()).is_err());
+}
+
+#[test]
+fn parse05() {
+    // `Node` has no `Drop`, so its children can be moved out of it.
+    let Ok(Node::Concat(left, right)) = parse(r"ab") else {
+        panic!("not a concatenation");
+    };
+    assert_eq!(*left, Node::Character('a'));
+    assert_eq!(*right, Node::Character('b'));
+
+    // The deep tree parsed before the error is dropped without overflowing the stack.
+    let pattern = format!("({}))", "a".repeat(200_000));
+    assert_eq!(
+        parse(&pattern),
+        Err(RegexError::UnbalancedParen { span: 200_002 })
+    );
+}
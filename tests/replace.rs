@@ -0,0 +1,31 @@
+use dfa_regex::Regex;
+
+#[test]
+fn replace_all01() {
+    let regex = Regex::new(r"a+").unwrap();
+    assert_eq!(regex.replace_all("baaab", "X"), "bXb");
+    assert_eq!(regex.replace_all("aba", "猫"), "猫b猫");
+    assert_eq!(regex.replace_all("bcd", "X"), "bcd");
+}
+
+#[test]
+fn replace_all02() {
+    let regex = Regex::new(r"x*").unwrap();
+    assert_eq!(regex.replace_all("ab", "-"), "-a-b-");
+    assert_eq!(regex.replace_all("", "-"), "-");
+}
+
+#[test]
+fn split01() {
+    let regex = Regex::new(r",").unwrap();
+    assert_eq!(regex.split("a,,b"), vec!["a", "", "b"]);
+    assert_eq!(regex.split(",a,"), vec!["", "a", ""]);
+    assert_eq!(regex.split("ab"), vec!["ab"]);
+    assert_eq!(regex.split(""), vec![""]);
+}
+
+#[test]
+fn split02() {
+    let regex = Regex::new(r"\s+").unwrap();
+    assert_eq!(regex.split("山  川\t海"), vec!["山", "川", "海"]);
+}